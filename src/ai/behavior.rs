@@ -0,0 +1,358 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+use bevy_mod_wanderlust::ControllerInput;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{PartInfo, PartTree, PartTreeRoot};
+use crate::factions::{nearest_hostile, Faction};
+use crate::utils::loaded_assets_by_name;
+
+pub struct BehaviorPlugin;
+
+impl Plugin for BehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BehaviorHandles>()
+            .init_resource::<BehaviorTable>()
+            .add_event::<BehaviorsLoadedEvent>()
+            .add_asset::<BehaviorDef>()
+            .init_asset_loader::<BehaviorLoader>()
+            .add_startup_system(load_behaviors)
+            .add_system(track_behaviors_loaded)
+            .add_system(run_behaviors);
+    }
+}
+
+/// An AI archetype: a behavior tree ticked from the top every frame.
+#[derive(Clone, Debug, Deserialize, Serialize, TypeUuid)]
+#[uuid = "9b6f2c41-7d3e-4e0a-b5c8-1f2a6d4e8c37"]
+pub struct BehaviorDef {
+    pub name: String,
+    pub root: NodeDef,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum NodeDef {
+    /// Runs its children in order until one doesn't succeed.
+    #[serde(rename = "sequence")]
+    Sequence { children: Vec<NodeDef> },
+    /// Runs its children in order until one doesn't fail.
+    #[serde(rename = "selector")]
+    Selector { children: Vec<NodeDef> },
+    #[serde(rename = "condition")]
+    Condition { condition: Condition },
+    #[serde(rename = "action")]
+    Action { action: Action },
+}
+
+/// Checks the agent's blackboard, succeeding if it holds and failing otherwise.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind")]
+pub enum Condition {
+    #[serde(rename = "has_target")]
+    HasTarget,
+    #[serde(rename = "target_within")]
+    TargetWithin { distance: f32 },
+    #[serde(rename = "target_beyond")]
+    TargetBeyond { distance: f32 },
+    /// Whether the target is within reach of the agent's longest-ranged weapon.
+    #[serde(rename = "in_weapon_range")]
+    InWeaponRange,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind")]
+pub enum Action {
+    /// Picks the nearest hostile robot as the target, failing if there's none within `range`.
+    #[serde(rename = "find_target")]
+    FindTarget { range: Option<f32> },
+    #[serde(rename = "move_to_target")]
+    MoveToTarget,
+    #[serde(rename = "move_from_target")]
+    MoveFromTarget,
+    #[serde(rename = "stop")]
+    Stop,
+    /// Holds between `band` short of the agent's weapon range and the range itself, circling the
+    /// target the whole time.
+    #[serde(rename = "kite")]
+    Kite { band: f32 },
+    /// Fires every weapon at the target, see `fire_enemy_weapons`.
+    #[serde(rename = "fire")]
+    Fire,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+/// Assigns a `BehaviorDef` to an agent by name.
+#[derive(Component, Clone, Debug)]
+pub struct Behavior {
+    pub archetype: String,
+}
+
+/// What an agent knows and has decided, kept between ticks of its behavior tree.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Blackboard {
+    pub target: Option<Entity>,
+    /// Where the target was when the tree last ran.
+    pub target_position: Vec2,
+    /// Whether the agent's weapons should fire at the target this frame.
+    pub firing: bool,
+    /// The reach of the agent's longest-ranged weapon, see `PartWeapon::range`.
+    pub weapon_range: f32,
+    /// Which way the agent circles its target, 1 for counterclockwise and -1 for clockwise. Picked
+    /// at random the first time it strafes.
+    pub strafe: f32,
+}
+
+#[derive(Default, Deref, DerefMut)]
+pub struct BehaviorHandles(Vec<Handle<BehaviorDef>>);
+
+#[derive(Default, Deref, DerefMut)]
+pub struct BehaviorTable(HashMap<String, BehaviorDef>);
+
+pub fn load_behaviors(assets: ResMut<AssetServer>, mut behaviors: ResMut<BehaviorHandles>) {
+    behaviors.0 = assets
+        .load_folder("toml/ai")
+        .unwrap()
+        .into_iter()
+        .map(|handle| handle.typed::<BehaviorDef>())
+        .collect();
+    info!("Loading behaviors...");
+}
+
+#[derive(Default)]
+pub struct BehaviorLoader;
+
+impl AssetLoader for BehaviorLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async {
+            let behavior = toml::from_slice::<BehaviorDef>(bytes)?;
+
+            info!("Behavior {} loaded", &behavior.name);
+
+            load_context.set_default_asset(LoadedAsset::new(behavior));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ai.toml"]
+    }
+}
+
+pub struct BehaviorsLoadedEvent;
+
+pub fn track_behaviors_loaded(
+    mut c: Commands,
+    assets: Res<AssetServer>,
+    behaviors: Res<Assets<BehaviorDef>>,
+    mut table: ResMut<BehaviorTable>,
+    handles: Option<Res<BehaviorHandles>>,
+    mut writer: EventWriter<BehaviorsLoadedEvent>,
+) {
+    let handles = match handles {
+        Some(handles) => handles,
+        None => return,
+    };
+    table.0 = match loaded_assets_by_name(
+        &assets,
+        &behaviors,
+        handles.as_slice(),
+        "behavior",
+        |behavior| behavior.name.as_str(),
+    ) {
+        Some(loaded) => loaded,
+        None => return,
+    };
+    c.remove_resource::<BehaviorHandles>();
+    writer.send(BehaviorsLoadedEvent);
+}
+
+type Roots<'w, 's> =
+    Query<'w, 's, (Entity, &'static GlobalTransform, &'static Faction), With<PartTreeRoot>>;
+
+/// Everything a node can read or change while the tree is ticked for one agent.
+struct Agent<'a, 'w, 's> {
+    position: Vec2,
+    faction: Faction,
+    blackboard: &'a mut Blackboard,
+    movement: Vec3,
+    roots: &'a Roots<'w, 's>,
+}
+
+impl NodeDef {
+    fn tick(&self, agent: &mut Agent) -> Status {
+        match self {
+            NodeDef::Sequence { children } => {
+                for child in children {
+                    match child.tick(agent) {
+                        Status::Success => continue,
+                        status => return status,
+                    }
+                }
+                Status::Success
+            }
+            NodeDef::Selector { children } => {
+                for child in children {
+                    match child.tick(agent) {
+                        Status::Failure => continue,
+                        status => return status,
+                    }
+                }
+                Status::Failure
+            }
+            NodeDef::Condition { condition } => match condition.check(agent) {
+                true => Status::Success,
+                false => Status::Failure,
+            },
+            NodeDef::Action { action } => action.run(agent),
+        }
+    }
+}
+
+impl Condition {
+    fn check(&self, agent: &Agent) -> bool {
+        let distance = agent.position.distance(agent.blackboard.target_position);
+        match self {
+            Condition::HasTarget => agent.blackboard.target.is_some(),
+            Condition::TargetWithin { distance: max } => {
+                agent.blackboard.target.is_some() && distance <= *max
+            }
+            Condition::TargetBeyond { distance: min } => {
+                agent.blackboard.target.is_some() && distance > *min
+            }
+            Condition::InWeaponRange => {
+                agent.blackboard.target.is_some() && distance <= agent.blackboard.weapon_range
+            }
+        }
+    }
+}
+
+impl Action {
+    fn run(&self, agent: &mut Agent) -> Status {
+        let to_target = (agent.blackboard.target_position - agent.position)
+            .normalize_or_zero()
+            .extend(0.0);
+        match self {
+            Action::FindTarget { range } => {
+                let target = nearest_hostile(agent.faction, agent.position, agent.roots.iter())
+                    .filter(|(_, pos)| range.map_or(true, |r| pos.distance(agent.position) <= r));
+                match target {
+                    Some((target, pos)) => {
+                        agent.blackboard.target = Some(target);
+                        agent.blackboard.target_position = pos;
+                        Status::Success
+                    }
+                    None => {
+                        agent.blackboard.target = None;
+                        Status::Failure
+                    }
+                }
+            }
+            Action::MoveToTarget | Action::MoveFromTarget | Action::Kite { .. } | Action::Fire
+                if agent.blackboard.target.is_none() =>
+            {
+                Status::Failure
+            }
+            Action::MoveToTarget => {
+                agent.movement = to_target;
+                Status::Running
+            }
+            Action::MoveFromTarget => {
+                agent.movement = -to_target;
+                Status::Running
+            }
+            Action::Kite { .. } if agent.blackboard.weapon_range <= 0.0 => Status::Failure,
+            Action::Kite { band } => {
+                let range = agent.blackboard.weapon_range;
+                let distance = agent.position.distance(agent.blackboard.target_position);
+                let radial = if distance > range {
+                    to_target
+                } else if distance < range - band {
+                    -to_target
+                } else {
+                    Vec3::ZERO
+                };
+
+                if agent.blackboard.strafe == 0.0 {
+                    agent.blackboard.strafe = match thread_rng().gen_bool(0.5) {
+                        true => 1.0,
+                        false => -1.0,
+                    };
+                }
+                let strafe = Vec3::new(-to_target.y, to_target.x, 0.0) * agent.blackboard.strafe;
+
+                agent.movement = (radial + strafe).normalize_or_zero();
+                Status::Running
+            }
+            Action::Stop => {
+                agent.movement = Vec3::ZERO;
+                Status::Success
+            }
+            Action::Fire => {
+                agent.blackboard.firing = true;
+                Status::Success
+            }
+        }
+    }
+}
+
+fn run_behaviors(
+    behaviors: Res<BehaviorTable>,
+    mut agents: Query<(
+        &Behavior,
+        &mut Blackboard,
+        &GlobalTransform,
+        &Faction,
+        &PartTree,
+        &mut ControllerInput,
+    )>,
+    roots: Roots,
+    parts: Query<&PartInfo>,
+) {
+    for (behavior, mut blackboard, tf, faction, tree, mut input) in agents.iter_mut() {
+        let def = match behaviors.get(&behavior.archetype) {
+            Some(def) => def,
+            None => continue,
+        };
+
+        // Forget targets that were destroyed, and follow the ones that weren't.
+        if let Some(target) = blackboard.target {
+            match roots.get(target) {
+                Ok((_, target_tf, _)) => {
+                    blackboard.target_position = target_tf.translation().truncate()
+                }
+                Err(_) => blackboard.target = None,
+            }
+        }
+        blackboard.firing = false;
+        blackboard.weapon_range = tree
+            .parts()
+            .filter_map(|part| parts.get(part).ok()?.weapon.as_ref())
+            .map(|weapon| weapon.range())
+            .fold(0.0, f32::max);
+
+        let mut agent = Agent {
+            position: tf.translation().truncate(),
+            faction: *faction,
+            blackboard: &mut *blackboard,
+            movement: Vec3::ZERO,
+            roots: &roots,
+        };
+        def.root.tick(&mut agent);
+        input.movement = agent.movement;
+    }
+}
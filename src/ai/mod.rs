@@ -1,13 +1,13 @@
 use bevy::prelude::*;
 
-mod aggressive;
+mod behavior;
 
-pub use aggressive::*;
+pub use behavior::*;
 
 pub struct AiPlugin;
 
 impl Plugin for AiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(AggressivePlugin);
+        app.add_plugin(BehaviorPlugin);
     }
 }
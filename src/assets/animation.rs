@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy_mod_wanderlust::ControllerInput;
+use integra::defs::*;
+
+use super::damage::PartDamaged;
+use super::parts::*;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(trigger_part_animations)
+            .add_system(animate_parts.after(trigger_part_animations));
+    }
+}
+
+/// Starts the event clips of parts that fired, took damage or were just attached.
+fn trigger_part_animations(
+    mut fired: EventReader<WeaponFired>,
+    mut damaged: EventReader<PartDamaged>,
+    mut attached: EventReader<PartAttached>,
+    mut sprites: Query<&mut PartSprite>,
+) {
+    let triggers = fired
+        .iter()
+        .map(|event| (event.part, AnimationTrigger::Shoot))
+        .chain(
+            damaged
+                .iter()
+                .map(|event| (event.part, AnimationTrigger::Damaged)),
+        )
+        .chain(
+            attached
+                .iter()
+                .map(|event| (event.part, AnimationTrigger::Attached)),
+        );
+
+    for (part, trigger) in triggers {
+        if let Ok(mut sprite) = sprites.get_mut(part) {
+            sprite.trigger(trigger);
+        }
+    }
+}
+
+/// Plays every part's current clip, switching between `move` and `idle` clips as its robot
+/// starts and stops moving.
+fn animate_parts(
+    time: Res<Time>,
+    trees: Query<(&PartTree, Option<&ControllerInput>)>,
+    mut parts: Query<(&mut PartSprite, &mut Handle<Image>)>,
+) {
+    let delta = time.delta_seconds();
+    for (tree, input) in trees.iter() {
+        let moving = input.map_or(false, |input| input.movement.length_squared() != 0.0);
+        for part in tree.parts() {
+            let (mut sprite, mut image) = match parts.get_mut(part) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            let next = sprite.advance(delta, moving);
+            if *image != *next {
+                *image = next.clone();
+            }
+        }
+    }
+}
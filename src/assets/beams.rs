@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use integra::defs::*;
+
+use crate::factions::Faction;
+use crate::CustomPhysicsData;
+
+use super::damage::*;
+use super::parts::*;
+use super::projectiles::Projectile;
+
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BeamFiredEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, apply_beams.before(apply_damage))
+            .add_system(despawn_orphaned_beams);
+    }
+}
+
+/// Sent every frame a beam weapon part is firing.
+pub struct BeamFiredEvent {
+    pub part: Entity,
+    pub direction: Vec3,
+}
+
+#[derive(Component)]
+pub struct BeamEmitter {
+    visual: Entity,
+}
+
+#[derive(Component)]
+pub struct BeamVisual;
+
+fn apply_beams(
+    mut c: Commands,
+    mut events: EventReader<BeamFiredEvent>,
+    ctx: Res<RapierContext>,
+    time: Res<Time>,
+    parts: Query<(
+        &GlobalTransform,
+        &PartDef,
+        &PartInfo,
+        &CustomPhysicsData,
+        Option<&Faction>,
+    )>,
+    physics: Query<(&CustomPhysicsData, Option<&Faction>)>,
+    projectiles: Query<(), With<Projectile>>,
+    emitters: Query<&BeamEmitter>,
+    targets: Query<(), With<PartStats>>,
+    roots: Query<&PartTreeRoot>,
+    mut visuals: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<BeamVisual>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (.., mut visibility) in visuals.iter_mut() {
+        visibility.is_visible = false;
+    }
+
+    for event in events.iter() {
+        let (tf, def, info, data, faction) = match parts.get(event.part) {
+            Ok(p) => p,
+            _ => continue,
+        };
+
+        let (range, dps, width, sprite, damage_type) = match &info.weapon {
+            Some(PartWeapon::Beam {
+                range,
+                dps,
+                width,
+                sprite,
+                damage_type,
+            }) => (*range, *dps, *width, sprite, *damage_type),
+            _ => continue,
+        };
+
+        // Beams start from the hardpoint the weapon sits on rather than the middle of its sprite.
+        let root = data.part_tree_root;
+        let origin = tf
+            .compute_matrix()
+            .transform_point3(Vec2::from(def.origin).extend(0.0));
+        let dir = event.direction.truncate().normalize_or_zero().extend(0.0);
+        if dir == Vec3::ZERO {
+            continue;
+        }
+
+        let hit = ctx.cast_ray(
+            origin,
+            dir,
+            range,
+            true,
+            QueryFilter::new().exclude_sensors().predicate(&|entity| {
+                if projectiles.contains(entity) {
+                    return false;
+                }
+                match physics.get(entity) {
+                    Ok((_, Some(other))) if faction.map_or(false, |f| f.is_allied(*other)) => false,
+                    Ok((other, _)) => other.part_tree_root != root && !other.disable_collision,
+                    _ => true,
+                }
+            }),
+        );
+        let length = hit.map_or(range, |(_, toi)| toi);
+
+        let transform = Transform::from_translation(origin + dir * length / 2.0 - Vec3::Z)
+            .with_rotation(Quat::from_rotation_arc_2d(Vec2::Y, dir.truncate()));
+        let size = Vec2::new(width, length);
+
+        // Each frame's share of damage is sent as is, `apply_damage` adds up the fractions.
+        if let Some((target, toi)) = hit.filter(|(entity, _)| targets.contains(*entity)) {
+            let rate = tree_power(root, &roots) * info.fire_rate;
+            damage.send(DamageEvent {
+                source: root,
+                target,
+                amount: dps * rate * time.delta_seconds(),
+                kind: damage_type,
+                position: origin + dir * toi,
+            });
+        }
+
+        // The first frame a weapon fires its visual is spawned already in place, so even a single
+        // frame of firing is drawn.
+        match emitters.get(event.part) {
+            Ok(emitter) => {
+                if let Ok((mut beam_transform, mut beam_sprite, mut visibility)) =
+                    visuals.get_mut(emitter.visual)
+                {
+                    *beam_transform = transform;
+                    beam_sprite.custom_size = Some(size);
+                    visibility.is_visible = true;
+                }
+            }
+            Err(_) => {
+                let visual = c
+                    .spawn_bundle(SpriteBundle {
+                        texture: sprite.clone(),
+                        sprite: Sprite {
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(BeamVisual)
+                    .id();
+                c.entity(event.part).insert(BeamEmitter { visual });
+            }
+        }
+    }
+}
+
+/// Removes beam visuals whose weapon part no longer exists.
+fn despawn_orphaned_beams(
+    mut c: Commands,
+    emitters: Query<&BeamEmitter>,
+    visuals: Query<Entity, With<BeamVisual>>,
+) {
+    for visual in visuals.iter() {
+        if !emitters.iter().any(|e| e.visual == visual) {
+            c.entity(visual).despawn();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+use integra::defs::*;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::loaded_assets_by_name;
+
+use super::parts::*;
+
+/// A complete robot build: a root part plus every part seated on its hardpoints.
+#[derive(Clone, Debug, Deserialize, Serialize, TypeUuid)]
+#[uuid = "5d0c8a57-3f3e-4a52-9d0b-2f6b0f0f5a11"]
+pub struct Blueprint {
+    pub name: String,
+    pub part: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BlueprintSlot>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlueprintSlot {
+    pub hardpoint: usize,
+    pub part: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BlueprintSlot>,
+}
+
+impl Blueprint {
+    /// Records the part tree starting at `root` as a blueprint.
+    pub fn capture(
+        name: impl Into<String>,
+        root: Entity,
+        parts: &Query<(&PartDef, &PartChildren)>,
+    ) -> Option<Self> {
+        let (def, children) = parts.get(root).ok()?;
+
+        Some(Self {
+            name: name.into(),
+            part: def.name.clone(),
+            children: capture_slots(children, parts),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), bevy::asset::Error> {
+        let text = toml::to_string_pretty(self)?;
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+fn capture_slots(
+    children: &PartChildren,
+    parts: &Query<(&PartDef, &PartChildren)>,
+) -> Vec<BlueprintSlot> {
+    children
+        .iter()
+        .enumerate()
+        .filter_map(|(hardpoint, child)| {
+            let (def, children) = parts.get((*child)?).ok()?;
+            Some(BlueprintSlot {
+                hardpoint,
+                part: def.name.clone(),
+                children: capture_slots(children, parts),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn spawn_slots<C: Component + Clone>(
+    parent: &mut EntityCommands,
+    parent_def: &PartDef,
+    slots: &[BlueprintSlot],
+    parts: &PartTable,
+    additional_comp: &Option<C>,
+) {
+    for slot in slots {
+        let part = match parts.get(&slot.part) {
+            Some(part) => part,
+            None => {
+                warn!(
+                    "Failed to spawn blueprint slot. Reason: Unknown part {}.",
+                    slot.part
+                );
+                continue;
+            }
+        };
+
+        // Slots that can't hold their part are skipped along with everything attached to them.
+        match parent_def.hardpoints.get(slot.hardpoint) {
+            Some(hardpoint) if part.def.fits_on(hardpoint) => (),
+            Some(_) => {
+                warn!(
+                    "Failed to spawn blueprint slot. Reason: Part {} does not fit hardpoint {} of part {}.",
+                    slot.part, slot.hardpoint, parent_def.name
+                );
+                continue;
+            }
+            None => {
+                warn!(
+                    "Failed to spawn blueprint slot. Reason: Invalid hardpoint index {} in part {}.",
+                    slot.hardpoint, parent_def.name
+                );
+                continue;
+            }
+        }
+
+        let mut child =
+            parent.spawn_part_on_hardpoint(part, slot.hardpoint, additional_comp.clone());
+        spawn_slots(
+            &mut child,
+            &part.def,
+            &slot.children,
+            parts,
+            additional_comp,
+        );
+    }
+}
+
+#[derive(Default, Deref, DerefMut)]
+pub struct BlueprintHandles(Vec<Handle<Blueprint>>);
+
+#[derive(Default, Deref, DerefMut)]
+pub struct BlueprintTable(HashMap<String, Blueprint>);
+
+pub fn load_blueprints(assets: ResMut<AssetServer>, mut blueprints: ResMut<BlueprintHandles>) {
+    blueprints.0 = assets
+        .load_folder("toml/blueprints")
+        .unwrap()
+        .into_iter()
+        .map(|handle| handle.typed::<Blueprint>())
+        .collect();
+    info!("Loading blueprints...");
+}
+
+#[derive(Default)]
+pub struct BlueprintLoader;
+
+impl AssetLoader for BlueprintLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async {
+            let blueprint = toml::from_slice::<Blueprint>(bytes)?;
+
+            info!("Blueprint {} loaded", &blueprint.name);
+
+            load_context.set_default_asset(LoadedAsset::new(blueprint));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["blueprint.toml"]
+    }
+}
+
+pub struct BlueprintsLoadedEvent;
+
+pub fn track_blueprints_loaded(
+    mut c: Commands,
+    assets: Res<AssetServer>,
+    blueprints: Res<Assets<Blueprint>>,
+    mut table: ResMut<BlueprintTable>,
+    handles: Option<Res<BlueprintHandles>>,
+    mut writer: EventWriter<BlueprintsLoadedEvent>,
+) {
+    let handles = match handles {
+        Some(handles) => handles,
+        None => return,
+    };
+    table.0 = match loaded_assets_by_name(
+        &assets,
+        &blueprints,
+        handles.as_slice(),
+        "blueprint",
+        |blueprint| blueprint.name.as_str(),
+    ) {
+        Some(loaded) => loaded,
+        None => return,
+    };
+    c.remove_resource::<BlueprintHandles>();
+    writer.send(BlueprintsLoadedEvent);
+}
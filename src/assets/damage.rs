@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use integra::defs::*;
+
+use crate::Player;
+
+use super::parts::*;
+use super::shields::*;
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<PartDamaged>()
+            .add_event::<PartDestroyed>()
+            .add_system_to_stage(CoreStage::PostUpdate, apply_damage)
+            .add_system_to_stage(CoreStage::PostUpdate, destroy_parts.after(apply_damage));
+    }
+}
+
+/// Asks for `amount` of `kind` damage to be dealt to the `target` part, hit at `position`.
+///
+/// Every weapon kind sends these instead of touching `PartStats` itself, so shields and
+/// resistances are applied in one place.
+#[derive(Clone, Debug)]
+pub struct DamageEvent {
+    /// The part tree that dealt the damage, if any.
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageType,
+    pub position: Vec3,
+}
+
+/// Sent after a part has lost hp.
+#[derive(Clone, Debug)]
+pub struct PartDamaged {
+    pub source: Option<Entity>,
+    pub part: Entity,
+    /// The hp actually lost, after shields and resistances.
+    pub amount: u32,
+    pub kind: DamageType,
+    pub position: Vec3,
+}
+
+/// Damage a part has taken that doesn't add up to a whole hp yet, kept so that small hits like a
+/// beam's each frame still wear it down through resistances.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct DamageRemainder(pub f32);
+
+/// Sent once when a part's hp reaches 0.
+#[derive(Clone, Debug)]
+pub struct PartDestroyed {
+    pub source: Option<Entity>,
+    pub part: Entity,
+    pub position: Vec3,
+}
+
+/// Resolves every `DamageEvent` against the shields covering the target, then its resistances.
+pub(super) fn apply_damage(
+    mut events: EventReader<DamageEvent>,
+    mut stats: Query<(&mut PartStats, &mut DamageRemainder)>,
+    parents: Query<&PartParent>,
+    mut shields: Query<(&mut Shield, &GlobalTransform)>,
+    mut damaged: EventWriter<PartDamaged>,
+    mut destroyed: EventWriter<PartDestroyed>,
+) {
+    for event in events.iter() {
+        // Parts that already died, including the player's root which stays around, don't drain
+        // the shields above them.
+        let (mut stats, mut remainder) = match stats.get_mut(event.target) {
+            Ok((stats, remainder)) if stats.hp > 0 => (stats, remainder),
+            _ => continue,
+        };
+
+        let remaining = absorb_damage(
+            event.target,
+            event.position,
+            event.amount,
+            &parents,
+            &mut shields,
+        );
+
+        let total = remainder.0 + stats.resistances.mitigate(remaining, event.kind);
+        let amount = total.floor();
+        remainder.0 = total - amount;
+        let amount = amount as u32;
+        let before = stats.hp;
+        stats.hp = stats.hp.saturating_sub(amount);
+
+        if stats.hp == before {
+            continue;
+        }
+
+        damaged.send(PartDamaged {
+            source: event.source,
+            part: event.target,
+            amount: before - stats.hp,
+            kind: event.kind,
+            position: event.position,
+        });
+
+        if stats.hp == 0 {
+            destroyed.send(PartDestroyed {
+                source: event.source,
+                part: event.target,
+                position: event.position,
+            });
+        }
+    }
+}
+
+/// Despawns destroyed parts, except for the player's root which is never despawned.
+fn destroy_parts(
+    mut c: Commands,
+    mut events: EventReader<PartDestroyed>,
+    player: Query<(), With<Player>>,
+) {
+    for event in events.iter() {
+        if !player.contains(event.part) {
+            c.despawn_part(event.part);
+        }
+    }
+}
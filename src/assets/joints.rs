@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use integra::defs::*;
+
+use super::parts::*;
+
+pub struct JointPlugin;
+
+impl Plugin for JointPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PartTornOff>().add_system(tear_off_parts);
+    }
+}
+
+/// Sent when the force on a part's joint exceeds its strength and the part is torn off.
+#[derive(Clone, Debug)]
+pub struct PartTornOff {
+    pub part: Entity,
+    pub parent: Entity,
+    pub force: f32,
+}
+
+/// Detaches parts whose joints took more force during the last physics step than they can hold.
+fn tear_off_parts(
+    mut c: Commands,
+    ctx: Res<RapierContext>,
+    trees: Query<&PartTree>,
+    parts: Query<(&PartDef, Option<&RapierImpulseJointHandle>)>,
+    mut events: EventWriter<PartTornOff>,
+) {
+    let dt = ctx.integration_parameters.dt;
+    if dt <= 0.0 {
+        return;
+    }
+
+    for tree in trees.iter() {
+        for member in tree.iter() {
+            let (parent, slot) = match member.parent {
+                Some(parent) => parent,
+                None => continue,
+            };
+
+            let (def, handle) = match parts.get(member.part) {
+                Ok((def, Some(handle))) => (def, handle),
+                _ => continue,
+            };
+            let hardpoint = match parts.get(parent) {
+                Ok((parent_def, _)) => match parent_def.hardpoints.get(slot) {
+                    Some(hardpoint) => hardpoint,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let strength = match def.joint_strength_on(hardpoint) {
+                Some(strength) => strength,
+                None => continue,
+            };
+
+            let joint = match ctx.impulse_joints.get(handle.0) {
+                Some(joint) => joint,
+                None => continue,
+            };
+            // Rapier works in scaled-down units, so the impulse is scaled back up to match the
+            // world units strengths are written in.
+            let force = joint.impulses.fixed_rows::<3>(0).norm() * ctx.physics_scale() / dt;
+
+            if force > strength {
+                c.detach_part(member.part);
+                events.send(PartTornOff {
+                    part: member.part,
+                    parent,
+                    force,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use bevy_rapier3d::prelude::*;
+use integra::defs::*;
+
+use crate::factions::Faction;
+use crate::CustomPhysicsData;
+
+use super::damage::*;
+use super::parts::*;
+use super::projectiles::collision_pair;
+
+pub struct MeleePlugin;
+
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(sync_melee_hitboxes)
+            .add_system(track_melee_contacts)
+            .add_system(apply_melee.after(track_melee_contacts));
+    }
+}
+
+/// A sensor attached to a melee weapon part, tracking everything it currently touches.
+#[derive(Component)]
+pub struct MeleeHitbox {
+    pub part: Entity,
+    pub contacts: Vec<Entity>,
+    size: Vec2,
+    offset: Vec2,
+}
+
+/// Keeps each part's hitbox in line with its melee weapon, including after a hot reload changes
+/// the hitbox or adds or removes the weapon.
+fn sync_melee_hitboxes(
+    mut c: Commands,
+    parts: Query<(Entity, &PartInfo, Option<&Children>), Changed<PartInfo>>,
+    hitboxes: Query<&MeleeHitbox>,
+) {
+    for (part, info, children) in parts.iter() {
+        let wanted = match &info.weapon {
+            Some(PartWeapon::Melee {
+                hitbox_size,
+                hitbox_offset,
+                ..
+            }) => Some((*hitbox_size, *hitbox_offset)),
+            _ => None,
+        };
+
+        let existing = children
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .find_map(|&child| Some((child, hitboxes.get(child).ok()?)));
+        if let Some((hitbox, current)) = existing {
+            if wanted == Some((current.size, current.offset)) {
+                continue;
+            }
+            c.entity(hitbox).despawn_recursive();
+        }
+
+        let (size, offset) = match wanted {
+            Some(wanted) => wanted,
+            None => continue,
+        };
+
+        c.entity(part).with_children(|parent| {
+            parent.spawn_bundle((
+                Transform::from_translation(offset.extend(0.0)),
+                GlobalTransform::default(),
+                Collider::cuboid(size.x / 2.0, size.y / 2.0, 50.0),
+                ColliderMassProperties::Density(0.0),
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                MeleeHitbox {
+                    part,
+                    contacts: vec![],
+                    size,
+                    offset,
+                },
+            ));
+        });
+    }
+}
+
+fn track_melee_contacts(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut hitboxes: Query<&mut MeleeHitbox>,
+) {
+    for event in collision_events.iter() {
+        let (hitbox, other, started) = match collision_pair(event, |e| hitboxes.contains(e)) {
+            Some(pair) => pair,
+            _ => continue,
+        };
+
+        let mut hitbox = hitboxes.get_mut(hitbox).unwrap();
+        if started {
+            hitbox.contacts.push(other);
+        } else {
+            hitbox.contacts.retain(|&c| c != other);
+        }
+    }
+}
+
+fn apply_melee(
+    hitboxes: Query<&MeleeHitbox>,
+    mut weapons: Query<(
+        &GlobalTransform,
+        &mut PartInfo,
+        &CustomPhysicsData,
+        Option<&Faction>,
+    )>,
+    mut targets: Query<
+        (
+            &GlobalTransform,
+            &CustomPhysicsData,
+            Option<&Faction>,
+            &mut ExternalImpulse,
+        ),
+        With<PartStats>,
+    >,
+    roots: Query<&PartTreeRoot>,
+    mut damage: EventWriter<DamageEvent>,
+    mut fired: EventWriter<WeaponFired>,
+) {
+    for hitbox in hitboxes.iter() {
+        let (tf, mut info, data, faction) = match weapons.get_mut(hitbox.part) {
+            Ok(w) => w,
+            _ => continue,
+        };
+
+        let rate = tree_power(data.part_tree_root, &roots) * info.fire_rate;
+        let (amount, damage_type, knockback, cooldown, last_hit) = match &mut info.weapon {
+            Some(PartWeapon::Melee {
+                damage,
+                damage_type,
+                knockback,
+                cooldown,
+                last_hit,
+                ..
+            }) => (*damage, *damage_type, *knockback, *cooldown, last_hit),
+            _ => continue,
+        };
+
+        if last_hit.elapsed().as_secs_f32() < cooldown / rate {
+            continue;
+        }
+
+        let target = hitbox
+            .contacts
+            .iter()
+            .find(|&&target| match targets.get(target) {
+                Ok((_, other, other_faction, _)) => {
+                    let allied = match (faction, other_faction) {
+                        (Some(a), Some(b)) => a.is_allied(*b),
+                        _ => false,
+                    };
+                    other.part_tree_root != data.part_tree_root
+                        && !other.disable_collision
+                        && !allied
+                }
+                _ => false,
+            });
+        let target = match target {
+            Some(&target) => target,
+            None => continue,
+        };
+
+        let (target_tf, _, _, mut target_impulse) = targets.get_mut(target).unwrap();
+        *last_hit = Instant::now();
+        fired.send(WeaponFired { part: hitbox.part });
+
+        let dir = (target_tf.translation() - tf.translation())
+            .truncate()
+            .normalize_or_zero()
+            .extend(0.0);
+        target_impulse.impulse += dir * knockback;
+
+        damage.send(DamageEvent {
+            source: data.part_tree_root,
+            target,
+            amount: amount as f32,
+            kind: damage_type,
+            position: target_tf.translation(),
+        });
+    }
+}
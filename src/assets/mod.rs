@@ -1,14 +1,29 @@
 use bevy::prelude::*;
 
+mod animation;
+mod beams;
+mod blueprints;
+mod damage;
+mod joints;
+mod melee;
 mod parts;
 mod projectiles;
+mod salvage;
+mod shields;
 
+pub use beams::BeamFiredEvent;
+pub use blueprints::{Blueprint, BlueprintTable, BlueprintsLoadedEvent};
+pub use damage::{DamageEvent, PartDamaged, PartDestroyed};
+pub use integra::defs::*;
+pub use joints::PartTornOff;
 pub use parts::{
-    DefSprite, Hardpoint, Order, Part, PartAnimation, PartBundle, PartChildren, PartCommandsExt,
-    PartDef, PartEntityCommandsExt, PartInfo, PartSprite, PartStats, PartTable, PartTreeRoot,
-    PartWeapon, PartWeaponDef, PartsLoadedEvent,
+    Part, PartAttached, PartBundle, PartChildren, PartClip, PartCommandsExt, PartEntityCommandsExt,
+    PartInfo, PartSprite, PartTable, PartTree, PartTreeMember, PartTreeRoot, PartTrees, PartWeapon,
+    PartsLoadedEvent, WeaponFired,
 };
 pub use projectiles::*;
+pub use salvage::Salvage;
+pub use shields::Shield;
 
 pub struct AssetPlugin;
 
@@ -16,21 +31,38 @@ impl Plugin for AssetPlugin {
     fn build(&self, app: &mut App) {
         let part_loader = parts::PartLoader::from_world(&mut app.world);
         app.add_plugin(ProjectilePlugin)
+            .add_plugin(animation::AnimationPlugin)
+            .add_plugin(damage::DamagePlugin)
+            .add_plugin(joints::JointPlugin)
+            .add_plugin(beams::BeamPlugin)
+            .add_plugin(melee::MeleePlugin)
+            .add_plugin(shields::ShieldPlugin)
+            .add_plugin(salvage::SalvagePlugin)
             .init_resource::<parts::PartHandles>()
             .init_resource::<parts::PartTable>()
+            .init_resource::<blueprints::BlueprintHandles>()
+            .init_resource::<blueprints::BlueprintTable>()
             .register_type::<Order>()
             .register_type::<Hardpoint>()
             .register_type::<PartDef>()
             .register_type::<Part>()
             .register_type::<parts::PartChildren>()
-            .register_type::<parts::PartStats>()
+            .register_type::<PartStats>()
             .register_type::<parts::PartTreeRoot>()
             .add_event::<parts::PartsLoadedEvent>()
-            .add_asset::<parts::PartDef>()
-            .add_asset::<parts::Part>()
+            .add_event::<parts::WeaponFired>()
+            .add_event::<parts::PartAttached>()
+            .add_event::<blueprints::BlueprintsLoadedEvent>()
+            .add_asset::<PartDef>()
+            .add_asset::<parts::PartTemplate>()
+            .add_asset::<blueprints::Blueprint>()
             .add_asset_loader(part_loader)
+            .init_asset_loader::<blueprints::BlueprintLoader>()
             .add_startup_system(parts::load_parts)
+            .add_startup_system(blueprints::load_blueprints)
             .add_system(parts::track_parts_loaded)
+            .add_system(parts::reload_parts)
+            .add_system(blueprints::track_blueprints_loaded)
             .add_system_to_stage(CoreStage::PreUpdate, parts::accumulate_part_stats);
     }
 }
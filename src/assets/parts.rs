@@ -1,148 +1,114 @@
+use super::blueprints::*;
+use super::damage::DamageRemainder;
 use super::projectiles::*;
 use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
-use bevy::ecs::system::EntityCommands;
+use bevy::ecs::system::{EntityCommands, SystemParam};
 use bevy::math::vec3;
 use bevy::prelude::*;
 use bevy::reflect::{FromReflect, TypeUuid};
+use bevy::render::render_resource::TextureFormat;
 use bevy::render::renderer::RenderDevice;
 use bevy::render::texture::{CompressedImageFormats, ImageType};
 use bevy::utils::{HashMap, Instant};
 use bevy_rapier3d::prelude::*;
-use serde::{Deserialize, Serialize};
+use integra::defs::*;
+use std::path::Path;
 
-use crate::utils::UtilCommandExt;
-use crate::{CustomPhysicsData, EnemyOwned, PlayerOwned};
+use crate::factions::Faction;
+use crate::utils::{UtilCommandExt, VecExt};
+use crate::CustomPhysicsData;
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-pub enum Order {
-    #[serde(rename = "above")]
-    Above,
-    #[serde(rename = "below")]
-    Below,
-}
-
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-pub struct Hardpoint {
-    pub position: (f32, f32),
-    pub direction: (f32, f32),
-    pub order: Order,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-#[serde(tag = "type")]
-pub enum DefAnimation {
-    #[serde(rename = "on move")]
-    OnMove { idle: String, sequence: Vec<String> },
-    #[serde(rename = "on shoot")]
-    OnShoot { idle: String, sequence: Vec<String> },
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct PartClip {
+    pub name: String,
+    pub trigger: AnimationTrigger,
+    pub frames: Vec<Handle<Image>>,
+    pub frame_time: f32,
+    pub mode: PlayMode,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-#[serde(tag = "type")]
-pub enum DefSprite {
-    #[serde(rename = "basic")]
-    Basic { path: String },
-    #[serde(rename = "animation")]
-    Animation { animation: DefAnimation },
+#[derive(Component, Clone, Debug, Reflect, FromReflect)]
+pub enum PartSprite {
+    Basic(Handle<Image>),
+    Animation {
+        idle: Handle<Image>,
+        clips: Vec<PartClip>,
+        /// The index of the clip being played, if any.
+        playing: Option<usize>,
+        /// How long the current clip has been playing, in seconds.
+        elapsed: f32,
+    },
 }
 
-impl DefSprite {
-    pub fn is_animation(&self) -> bool {
+impl PartSprite {
+    pub fn idle(&self) -> &Handle<Image> {
         match self {
-            DefSprite::Basic { .. } => false,
-            DefSprite::Animation { .. } => true,
+            PartSprite::Basic(sprite) => sprite,
+            PartSprite::Animation { idle, .. } => idle,
         }
     }
-}
-
-#[derive(Component, Copy, Clone, Default, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-#[reflect(Component)]
-pub struct PartStats {
-    pub hp: u32,
-    pub speed: Option<f32>,
-    pub acceleration: Option<f32>,
-    pub force: Option<f32>,
-}
-
-impl std::ops::Add<PartStats> for PartStats {
-    type Output = Self;
 
-    fn add(self, rhs: PartStats) -> Self::Output {
-        Self {
-            hp: self.hp + rhs.hp,
-            speed: Some(self.speed.unwrap_or_default() + rhs.speed.unwrap_or_default()),
-            acceleration: Some(
-                self.acceleration.unwrap_or_default() + rhs.acceleration.unwrap_or_default(),
-            ),
-            force: Some(self.force.unwrap_or_default() + rhs.force.unwrap_or_default()),
+    /// Starts the clip for `trigger` from its first frame, unless it's already playing.
+    pub fn trigger(&mut self, trigger: AnimationTrigger) {
+        if let PartSprite::Animation {
+            clips,
+            playing,
+            elapsed,
+            ..
+        } = self
+        {
+            let next = clips.iter().position(|clip| clip.trigger == trigger);
+            if next.is_some() && next != *playing {
+                *playing = next;
+                *elapsed = 0.0;
+            }
         }
     }
-}
 
-impl std::ops::AddAssign<PartStats> for PartStats {
-    fn add_assign(&mut self, rhs: PartStats) {
-        *self = *self + rhs;
-    }
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-#[serde(tag = "type")]
-pub enum PartWeaponDef {
-    #[serde(rename = "projectile")]
-    Projectile {
-        spread: f32,
-        cooldown: f32,
-        projectile: WeaponProjectileDef,
-    },
-}
+    /// Advances the animation by `delta` seconds and returns the frame to show.
+    ///
+    /// `moving` picks between the `move` and `idle` clips, but only once any event clip has
+    /// finished.
+    pub fn advance(&mut self, delta: f32, moving: bool) -> &Handle<Image> {
+        let (idle, clips, playing, elapsed) = match self {
+            PartSprite::Basic(sprite) => return sprite,
+            PartSprite::Animation {
+                idle,
+                clips,
+                playing,
+                elapsed,
+            } => (idle, clips, playing, elapsed),
+        };
 
-#[derive(Component, Clone, Debug, Deserialize, Serialize, TypeUuid, Reflect, FromReflect)]
-#[uuid = "c3eda9f1-b731-4156-ae80-173056a0f25b"]
-pub struct PartDef {
-    pub name: String,
-    pub origin: (f32, f32),
-    pub direction: (f32, f32),
-    pub stay_upright: Option<bool>,
-    pub chassis: Option<bool>,
-    pub sprite: DefSprite,
-    pub stats: PartStats,
-    pub hardpoints: Vec<Hardpoint>,
-    pub weapon: Option<PartWeaponDef>,
-}
-
-impl PartDef {
-    pub fn hardpoints(&self) -> impl Iterator<Item = (Vec2, Vec2, Order)> + '_ {
-        self.hardpoints.iter().map(move |point| {
-            (
-                Vec2::from(point.position),
-                Vec2::from(point.direction).normalize(),
-                point.order,
-            )
-        })
-    }
-}
+        if playing.map_or(true, |i| clips[i].trigger.is_state()) {
+            let state = match moving {
+                true => AnimationTrigger::Move,
+                false => AnimationTrigger::Idle,
+            };
+            let next = clips.iter().position(|clip| clip.trigger == state);
+            if next != *playing {
+                *playing = next;
+                *elapsed = 0.0;
+            }
+        }
 
-#[derive(Clone, Debug, Reflect, FromReflect)]
-pub enum PartAnimation {
-    OnMove {
-        idle: Handle<Image>,
-        sequence: Vec<Handle<Image>>,
-    },
-    OnShoot {
-        idle: Handle<Image>,
-        sequence: Vec<Handle<Image>>,
-    },
-}
+        let clip = match *playing {
+            Some(i) => &clips[i],
+            None => return idle,
+        };
 
-#[derive(Component, Clone, Debug, Reflect, FromReflect)]
-pub enum PartSprite {
-    Basic(Handle<Image>),
-    Animation {
-        current: usize,
-        rate: usize,
-        timer: usize,
-        anim: PartAnimation,
-    },
+        *elapsed += delta;
+        let frame = (*elapsed / clip.frame_time) as usize;
+        match clip.mode {
+            PlayMode::Loop => clip.frames.wrapping_get(frame).unwrap(),
+            PlayMode::Once if frame < clip.frames.len() => &clip.frames[frame],
+            PlayMode::Once => {
+                *playing = None;
+                *elapsed = 0.0;
+                idle
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, Reflect, FromReflect)]
@@ -153,6 +119,38 @@ pub enum PartWeapon {
         last_shot: Instant,
         projectile: WeaponProjectile,
     },
+    Beam {
+        range: f32,
+        dps: f32,
+        width: f32,
+        sprite: Handle<Image>,
+        damage_type: DamageType,
+    },
+    Melee {
+        damage: u32,
+        damage_type: DamageType,
+        knockback: f32,
+        cooldown: f32,
+        last_hit: Instant,
+        hitbox_size: Vec2,
+        hitbox_offset: Vec2,
+    },
+}
+
+impl PartWeapon {
+    /// How far the weapon reaches. Projectiles are only counted at their launch speed, so this
+    /// errs short for ones that speed up.
+    pub fn range(&self) -> f32 {
+        match self {
+            PartWeapon::Projectile { projectile, .. } => projectile.velocity * projectile.lifetime,
+            PartWeapon::Beam { range, .. } => *range,
+            PartWeapon::Melee {
+                hitbox_size,
+                hitbox_offset,
+                ..
+            } => hitbox_offset.length() + hitbox_size.max_element() / 2.0,
+        }
+    }
 }
 
 #[derive(Clone, Debug, TypeUuid, Reflect, FromReflect)]
@@ -162,6 +160,45 @@ pub struct Part {
     pub sprite: PartSprite,
     pub size: (u32, u32),
     pub weapon: Option<PartWeapon>,
+    /// Built from `def.collider` by the loader, `None` for a box the size of the sprite.
+    #[reflect(ignore)]
+    pub shape: Option<Collider>,
+}
+
+/// How far colliders extend on either side of the 2D plane.
+const COLLIDER_HALF_DEPTH: f32 = 50.0;
+
+impl Part {
+    pub fn collider(&self) -> Collider {
+        match &self.shape {
+            Some(shape) => shape.clone(),
+            None => Collider::cuboid(
+                self.size.0 as f32 / 2.0,
+                self.size.1 as f32 / 2.0,
+                COLLIDER_HALF_DEPTH,
+            ),
+        }
+    }
+
+    pub fn mass_properties(&self) -> ColliderMassProperties {
+        let mass = self.def.stats.mass;
+        let center = match self.def.center_of_mass {
+            Some(center) => Vec2::from(center),
+            None => return ColliderMassProperties::Mass(mass),
+        };
+
+        // The inertia of the collider's actual shape at unit density, scaled to the part's mass.
+        let shape = MassProperties::from_rapier(self.collider().raw.mass_properties(1.0), 1.0);
+        if shape.mass <= 0.0 {
+            return ColliderMassProperties::Mass(mass);
+        }
+        ColliderMassProperties::MassProperties(MassProperties {
+            local_center_of_mass: center.extend(0.0),
+            mass,
+            principal_inertia: shape.principal_inertia * (mass / shape.mass),
+            principal_inertia_local_frame: shape.principal_inertia_local_frame,
+        })
+    }
 }
 
 #[derive(Clone, Component, Deref, DerefMut, Reflect, FromReflect)]
@@ -170,43 +207,285 @@ pub struct PartChildren(Vec<Option<Entity>>);
 #[derive(Clone, Component, Deref, DerefMut, Reflect, FromReflect)]
 pub struct PartParent(Entity);
 
-#[derive(Component, Clone, Default, Reflect, FromReflect)]
+#[derive(Component, Clone, Reflect, FromReflect)]
 #[reflect(Component)]
 pub struct PartTreeRoot {
     pub cumulative_stats: PartStats,
+    /// How much of the tree's energy draw its generators cover, from 0 to 1. Weapons fire slower,
+    /// legs push weaker and shields recharge slower as it drops.
+    pub power: f32,
 }
 
-pub fn accumulate_part_stats(
-    mut roots: Query<(Entity, &mut PartTreeRoot)>,
-    parts: Query<(&PartStats, Option<&PartChildren>)>,
-) {
-    let mut stack = vec![];
-    for (root_id, mut root) in roots.iter_mut() {
-        root.cumulative_stats = default();
-        stack.push(root_id);
+impl Default for PartTreeRoot {
+    fn default() -> Self {
+        Self {
+            cumulative_stats: default(),
+            power: 1.0,
+        }
+    }
+}
+
+/// The power of the tree rooted at `root`, or full power for parts outside of any tree.
+pub fn tree_power(root: Option<Entity>, roots: &Query<&PartTreeRoot>) -> f32 {
+    root.and_then(|root| roots.get(root).ok())
+        .map_or(1.0, |root| root.power)
+}
 
-        while !stack.is_empty() {
-            let next = stack.pop().unwrap();
+/// A flattened, depth-first index of every part in a tree, kept on the tree's topmost part.
+///
+/// `attach_part` and `detach_part` rebuild it whenever the tree changes shape, so systems can
+/// visit a whole tree without walking `PartChildren` every frame.
+#[derive(Component, Clone, Debug, Default)]
+pub struct PartTree {
+    members: Vec<PartTreeMember>,
+}
 
-            let (stats, children) = parts.get(next).unwrap();
+#[derive(Clone, Copy, Debug)]
+pub struct PartTreeMember {
+    pub part: Entity,
+    /// How many joints away from the top of the tree the part is.
+    pub depth: usize,
+    /// The part this one is attached to, and the index of the hardpoint it sits on.
+    pub parent: Option<(Entity, usize)>,
+}
 
-            children.map(|children| {
-                children
-                    .iter()
-                    .filter_map(|c| c.as_ref())
-                    .for_each(|&child| {
-                        stack.push(child);
-                    })
+impl PartTree {
+    fn single(part: Entity) -> Self {
+        Self {
+            members: vec![PartTreeMember {
+                part,
+                depth: 0,
+                parent: None,
+            }],
+        }
+    }
+
+    fn build(world: &World, top: Entity) -> Self {
+        let mut members = vec![];
+        let mut stack = vec![(top, 0, None)];
+        while let Some((part, depth, parent)) = stack.pop() {
+            members.push(PartTreeMember {
+                part,
+                depth,
+                parent,
             });
 
-            root.cumulative_stats += *stats;
+            if let Some(children) = world.get::<PartChildren>(part) {
+                // Pushed in reverse so children come back out in hardpoint order.
+                for (slot, child) in children.iter().enumerate().rev() {
+                    if let Some(child) = child {
+                        stack.push((*child, depth + 1, Some((part, slot))));
+                    }
+                }
+            }
+        }
+
+        Self { members }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PartTreeMember> {
+        self.members.iter()
+    }
+
+    pub fn parts(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.members.iter().map(|member| member.part)
+    }
+
+    /// `part` and everything attached below it, or nothing if `part` isn't in this tree.
+    pub fn subtree(&self, part: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let start = self
+            .members
+            .iter()
+            .position(|member| member.part == part)
+            .unwrap_or(self.members.len());
+        let depth = self.members.get(start).map_or(0, |member| member.depth);
+
+        self.members[start..]
+            .iter()
+            .enumerate()
+            .take_while(move |(i, member)| *i == 0 || member.depth > depth)
+            .map(|(_, member)| member.part)
+    }
+}
+
+/// Looks up the `PartTree` of any part, not just the top of its tree.
+#[derive(SystemParam)]
+pub struct PartTrees<'w, 's> {
+    trees: Query<'w, 's, &'static PartTree>,
+    parents: Query<'w, 's, &'static PartParent>,
+}
+
+impl<'w, 's> PartTrees<'w, 's> {
+    pub fn tree(&self, part: Entity) -> Option<&PartTree> {
+        let mut top = part;
+        while let Ok(parent) = self.parents.get(top) {
+            top = parent.0;
+        }
+        self.trees.get(top).ok()
+    }
+
+    /// `part` and everything attached below it.
+    pub fn subtree(&self, part: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.tree(part)
+            .into_iter()
+            .flat_map(move |tree| tree.subtree(part))
+    }
+}
+
+/// Breaks `part` off its parent, leaving it and everything attached to it as a tree of its own.
+fn detach_part(world: &mut World, part: Entity) {
+    let entity = match world.get_entity(part) {
+        Some(e) => e,
+        None => return,
+    };
+    let top = tree_top(world, part);
+
+    if let Some(parent) = entity.get::<PartParent>() {
+        let parent_id = parent.0;
+        drop(parent);
+
+        if let Some(mut parent) = world.get_entity_mut(parent_id) {
+            if let Some(mut children) = parent.get_mut::<PartChildren>() {
+                _ = children
+                    .iter()
+                    .position(|e| e.is_some() && e.unwrap() == part)
+                    .map(|idx| children[idx] = None)
+            }
+        }
+    }
+
+    let mut children = vec![];
+    if let Some(part_children) = world.entity(part).get::<PartChildren>() {
+        children = part_children.iter().filter_map(|&c| c).collect();
+        let mut stack = vec![];
+        for &child in children.iter() {
+            let mut child = world.entity_mut(child);
+            child.remove::<PartParent>();
+            child.remove::<ImpulseJoint>();
+            child.insert(LockedAxes::TRANSLATION_LOCKED_Z | LockedAxes::ROTATION_LOCKED);
+            let id = child.id();
+            stack.clear();
+            stack.push(id);
+            while !stack.is_empty() {
+                let next = stack.pop().unwrap();
+                let mut next = world.entity_mut(next);
+                next.remove::<Faction>();
+                next.get::<PartChildren>().map(|children| {
+                    children
+                        .iter()
+                        .filter_map(|&c| c)
+                        .for_each(|c| stack.push(c))
+                });
+            }
         }
+
+        world
+            .entity_mut(part)
+            .get_mut::<PartChildren>()
+            .unwrap()
+            .iter_mut()
+            .for_each(|c| *c = None);
+    }
+
+    let id = part;
+    let mut part = world.entity_mut(id);
+    part.remove::<ImpulseJoint>();
+    part.remove::<PartParent>();
+    part.get_mut::<CustomPhysicsData>().unwrap().part_tree_root = None;
+
+    if top != id {
+        rebuild_part_tree(world, top);
+    }
+    rebuild_part_tree(world, id);
+    for child in children {
+        rebuild_part_tree(world, child);
+    }
+}
+
+fn tree_top(world: &World, mut part: Entity) -> Entity {
+    while let Some(parent) = world.get::<PartParent>(part) {
+        part = parent.0;
+    }
+    part
+}
+
+fn rebuild_part_tree(world: &mut World, part: Entity) {
+    let top = tree_top(world, part);
+    let tree = PartTree::build(world, top);
+    world.entity_mut(top).insert(tree);
+}
+
+/// Sums the stats of every part in each tree into its `PartTreeRoot`, after applying the
+/// modifiers that reach each part.
+pub fn accumulate_part_stats(
+    mut roots: Query<(&PartTree, &mut PartTreeRoot)>,
+    mut parts: Query<(&PartStats, &PartDef, &mut PartInfo)>,
+) {
+    for (tree, mut root) in roots.iter_mut() {
+        let members = &tree.members;
+        let mut modifiers = vec![StatModifiers::default(); members.len()];
+        for (i, member) in members.iter().enumerate() {
+            let def = match parts.get(member.part) {
+                Ok((_, def, _)) => def,
+                Err(_) => continue,
+            };
+
+            for modifier in def.modifiers.iter() {
+                let targets = match modifier.scope {
+                    ModifierScope::Part => i..i + 1,
+                    // Members are in depth-first order, so a subtree is the run of deeper
+                    // members right after its top.
+                    ModifierScope::Subtree => {
+                        let below = members[i + 1..]
+                            .iter()
+                            .take_while(|other| other.depth > member.depth)
+                            .count();
+                        i..i + 1 + below
+                    }
+                    ModifierScope::Root => 0..members.len(),
+                };
+                for target in modifiers[targets].iter_mut() {
+                    target.add(modifier);
+                }
+            }
+        }
+
+        root.cumulative_stats = default();
+        for (member, modifiers) in members.iter().zip(modifiers) {
+            let (stats, _, mut info) = match parts.get_mut(member.part) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            root.cumulative_stats += modifiers.apply(*stats);
+            let fire_rate = modifiers.fire_rate();
+            if info.fire_rate != fire_rate {
+                info.fire_rate = fire_rate;
+            }
+        }
+        root.power = root.cumulative_stats.power();
     }
 }
 
 #[derive(Component, Clone, Debug, Reflect, FromReflect)]
 pub struct PartInfo {
     pub weapon: Option<PartWeapon>,
+    /// Multiplies how often the weapon fires, from the modifiers reaching the part.
+    pub fire_rate: f32,
+}
+
+/// Sent whenever a weapon part shoots, swings or fires its beam.
+#[derive(Clone, Debug)]
+pub struct WeaponFired {
+    pub part: Entity,
+}
+
+/// Sent once `attach_part` has joined a part onto a hardpoint.
+#[derive(Clone, Debug)]
+pub struct PartAttached {
+    pub part: Entity,
+    pub parent: Entity,
+    pub hardpoint: usize,
 }
 
 #[derive(Bundle, Clone)]
@@ -230,23 +509,18 @@ pub struct PartBundle {
     pub gravity: GravityScale,
     pub damping: Damping,
     pub locked_axes: LockedAxes,
+    pub impulse: ExternalImpulse,
+    pub damage_remainder: DamageRemainder,
     pub name: Name,
 }
 
 impl PartBundle {
     pub fn new(part: &Part) -> Self {
-        let image = match &part.sprite {
-            PartSprite::Basic(sprite) => sprite,
-            PartSprite::Animation { anim, .. } => match anim {
-                PartAnimation::OnMove { idle, .. } => idle,
-                PartAnimation::OnShoot { idle, .. } => idle,
-            },
-        };
-
         Self {
             def: part.def.clone(),
             info: PartInfo {
                 weapon: part.weapon.clone(),
+                fire_rate: 1.0,
             },
             stats: part.def.stats.clone(),
             part_sprite: part.sprite.clone(),
@@ -255,15 +529,15 @@ impl PartBundle {
                     .take(part.def.hardpoints.len())
                     .collect(),
             ),
-            image: image.clone(),
+            image: part.sprite.idle().clone(),
             sprite: Sprite::default(),
-            collider: Collider::cuboid(part.size.0 as f32 / 2.0, part.size.1 as f32 / 2.0, 50.0),
+            collider: part.collider(),
             custom_data: CustomPhysicsData {
                 part_tree_root: None,
                 disable_collision: false,
             },
             active_hooks: ActiveHooks::FILTER_CONTACT_PAIRS,
-            mass_properties: ColliderMassProperties::Mass(1.0),
+            mass_properties: part.mass_properties(),
             transform: Transform::from_xyz(-part.def.origin.0, -part.def.origin.1, 0.0),
             global_transform: default(),
             visibility: default(),
@@ -275,23 +549,37 @@ impl PartBundle {
                 angular_damping: crate::DAMPING_FACTOR,
             },
             locked_axes: LockedAxes::TRANSLATION_LOCKED_Z,
+            impulse: default(),
+            damage_remainder: default(),
             name: part.def.name.clone().into(),
         }
     }
 }
 
 #[derive(Default, Deref, DerefMut)]
-pub struct PartHandles(Vec<Handle<Part>>);
+pub struct PartHandles(Vec<Handle<PartTemplate>>);
 
 #[derive(Default, Deref, DerefMut)]
 pub struct PartTable(HashMap<String, Part>);
 
+/// A part file as loaded, before `track_parts_loaded` merges in the part it extends.
+#[derive(Debug, TypeUuid)]
+#[uuid = "5d1f3a6e-8c0b-4f8e-9a47-2e6b1c93d0f4"]
+pub struct PartTemplate {
+    source: PartSource,
+    /// Every image named in the file, by path.
+    images: HashMap<String, Handle<Image>>,
+    /// Images that failed to load, reported along with everything else wrong with the part once
+    /// it's built.
+    errors: PartErrors,
+}
+
 pub fn load_parts(assets: ResMut<AssetServer>, mut parts: ResMut<PartHandles>) {
     parts.0 = assets
         .load_folder("toml/parts")
         .unwrap()
         .into_iter()
-        .map(|handle| handle.typed::<Part>())
+        .map(|handle| handle.typed::<PartTemplate>())
         .collect();
     info!("Loading parts...");
 }
@@ -319,186 +607,569 @@ impl AssetLoader for PartLoader {
         load_context: &'a mut LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
         Box::pin(async {
-            let def = toml::from_slice::<PartDef>(bytes)?;
-
-            let sprite_paths = match &def.sprite {
-                DefSprite::Basic { path } => vec![path],
-                DefSprite::Animation { animation } => match animation {
-                    DefAnimation::OnMove { idle, sequence } => {
-                        sequence.into_iter().chain([idle]).collect()
-                    }
-                    DefAnimation::OnShoot { idle, sequence } => {
-                        sequence.into_iter().chain([idle]).collect()
-                    }
-                },
+            let file = load_context.path().to_path_buf();
+            let source = match std::str::from_utf8(bytes) {
+                Ok(text) => PartSource::parse(&file, text)?,
+                Err(e) => {
+                    let mut errors = PartErrors::default();
+                    errors.push(&file, "", e.to_string());
+                    return Err(errors.into());
+                }
             };
 
-            let mut sprites = Vec::with_capacity(sprite_paths.len());
-            let mut size = None;
+            // The file may only be part of a definition, so its images are decoded here and
+            // the sprites are put together once every file it extends has loaded.
+            let mut errors = PartErrors::default();
+            let mut images = HashMap::default();
+            for (field, path) in source.image_paths() {
+                if images.contains_key(&path) {
+                    continue;
+                }
 
-            for path in sprite_paths.iter() {
-                let ext = std::path::Path::new(path)
+                let ext = std::path::Path::new(&path)
                     .extension()
-                    .ok_or(bevy::asset::Error::msg("Sprite has invalid extension"))?
-                    .to_str()
-                    .ok_or(bevy::asset::Error::msg("Sprite has invalid extension"))?;
-                let bytes = load_context.read_asset_bytes(path).await?;
+                    .and_then(|e| e.to_str())
+                    .unwrap_or_default();
+                let bytes = match load_context.read_asset_bytes(&path).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        errors.push(&file, field, e.to_string());
+                        continue;
+                    }
+                };
 
-                let image = Image::from_buffer(
+                let image = match Image::from_buffer(
                     &bytes,
                     ImageType::Extension(ext),
                     self.supported_compressed_formats,
                     true,
-                )?;
-
-                let descriptor = &image.texture_descriptor;
-                match size {
-                    Some(size) => {
-                        debug_assert_eq!(size, (descriptor.size.width, descriptor.size.height))
+                ) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        errors.push(&file, field, e.to_string());
+                        continue;
                     }
-                    None => size = Some((descriptor.size.width, descriptor.size.height)),
-                }
+                };
 
-                sprites.push(image);
+                let handle = load_context
+                    .set_labeled_asset(&format!("image{}", images.len()), LoadedAsset::new(image));
+                images.insert(path, handle);
             }
 
-            let size = size.unwrap();
+            let paths = images.keys().cloned().collect::<Vec<_>>();
+            let mut asset = LoadedAsset::new(PartTemplate {
+                source,
+                images,
+                errors,
+            });
+            for path in paths {
+                asset.add_dependency((&path).into());
+            }
 
-            let mut sprites = sprites
-                .into_iter()
-                .enumerate()
-                .map(|(i, sprite)| {
-                    load_context
-                        .set_labeled_asset(&format!("sprite{}", i), LoadedAsset::new(sprite))
-                })
-                .collect::<Vec<_>>();
-
-            let sprite = match &def.sprite {
-                DefSprite::Basic { .. } => PartSprite::Basic(sprites.remove(0)),
-                DefSprite::Animation { animation } => match animation {
-                    DefAnimation::OnMove { .. } => {
-                        let idle = sprites.pop().unwrap();
-                        let sequence = sprites;
-                        PartSprite::Animation {
-                            current: 0,
-                            rate: 5,
-                            timer: 0,
-                            anim: PartAnimation::OnMove { idle, sequence },
-                        }
-                    }
-                    DefAnimation::OnShoot { .. } => {
-                        let idle = sprites.pop().unwrap();
-                        let sequence = sprites;
-                        PartSprite::Animation {
-                            current: 0,
-                            rate: 5,
-                            timer: 0,
-                            anim: PartAnimation::OnShoot { idle, sequence },
-                        }
-                    }
-                },
-            };
+            load_context.set_default_asset(asset);
 
-            let weapon = match &def.weapon {
-                Some(PartWeaponDef::Projectile {
-                    projectile,
-                    spread,
-                    cooldown,
-                }) => {
-                    let sprite = {
-                        let ext = std::path::Path::new(&projectile.sprite_path)
-                            .extension()
-                            .ok_or(bevy::asset::Error::msg("Sprite has invalid extension"))?
-                            .to_str()
-                            .ok_or(bevy::asset::Error::msg("Sprite has invalid extension"))?;
-                        let bytes = load_context
-                            .read_asset_bytes(&projectile.sprite_path)
-                            .await?;
-
-                        Image::from_buffer(
-                            &bytes,
-                            ImageType::Extension(ext),
-                            self.supported_compressed_formats,
-                            true,
-                        )?
-                    };
-
-                    let size = sprite.size().as_uvec2().into();
-
-                    let sprite =
-                        load_context.set_labeled_asset("projectile", LoadedAsset::new(sprite));
-
-                    Some(PartWeapon::Projectile {
-                        spread: *spread,
-                        cooldown: *cooldown,
-                        last_shot: Instant::now(),
-                        projectile: WeaponProjectile {
-                            sprite,
-                            size,
-                            damage: projectile.damage,
-                            velocity: projectile.velocity.unwrap_or_default(),
-                            acceleration: projectile.acceleration.unwrap_or_default(),
-                        },
-                    })
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["part.toml"]
+    }
+}
+
+/// The images a resolved part can use: those of its own file first, then those of the parts it
+/// extends, in order.
+struct PartImages<'a> {
+    templates: Vec<&'a PartTemplate>,
+    assets: &'a Assets<Image>,
+}
+
+impl<'a> PartImages<'a> {
+    fn get(&self, path: &str) -> Option<(Handle<Image>, &'a Image)> {
+        let handle = self
+            .templates
+            .iter()
+            .copied()
+            .find_map(|template| template.images.get(path))?;
+        self.assets.get(handle).map(|image| (handle.clone(), image))
+    }
+
+    fn errors(&self) -> PartErrors {
+        PartErrors(
+            self.templates
+                .iter()
+                .flat_map(|template| template.errors.iter().cloned())
+                .collect(),
+        )
+    }
+}
+
+fn build_part(def: PartDef, file: &Path, images: &PartImages) -> Result<Part, PartErrors> {
+    // Everything wrong with the part is collected before giving up, so it can all be fixed at once.
+    let mut errors = images.errors();
+    let images_failed = !errors.is_empty();
+    if let Err(e) = def.validate(file) {
+        errors.extend(e.0);
+    }
+
+    let mut sprites = vec![];
+    let mut size = None;
+
+    for (field, path) in def.sprite_paths() {
+        let (handle, image) = match images.get(path) {
+            Some(image) => image,
+            None => {
+                // Images that failed to load already explain themselves.
+                if !images_failed {
+                    errors.push(file, field, format!("sprite `{path}` was not loaded"));
                 }
-                None => None,
-            };
+                continue;
+            }
+        };
 
-            info!("Part {} loaded", &def.name);
+        let descriptor = &image.texture_descriptor;
+        let frame_size = (descriptor.size.width, descriptor.size.height);
+        match size {
+            Some(size) if size != frame_size => errors.push(
+                file,
+                field,
+                format!(
+                    "frame is {}x{} but the first frame is {}x{}",
+                    frame_size.0, frame_size.1, size.0, size.1
+                ),
+            ),
+            Some(_) => (),
+            None => size = Some(frame_size),
+        }
 
-            let sprite_paths = sprite_paths.into_iter().cloned().collect::<Vec<_>>();
+        sprites.push((handle, image));
+    }
 
-            let mut asset = LoadedAsset::new(Part {
-                def,
+    let shape = match (&def.collider, size) {
+        (Some(collider), Some(_)) => {
+            let images = sprites.iter().map(|&(_, image)| image).collect::<Vec<_>>();
+            match build_collider(collider, &images) {
+                Ok(shape) => Some(shape),
+                Err(e) => {
+                    errors.push(file, "collider", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let weapon_sprite = match def.weapon.as_ref().and_then(|w| w.sprite_path()) {
+        Some((field, path)) => match images.get(path) {
+            Some((handle, image)) => {
+                let size: (u32, u32) = image.size().as_uvec2().into();
+                Some((handle, size))
+            }
+            None => {
+                if !images_failed {
+                    errors.push(file, field, format!("sprite `{path}` was not loaded"));
+                }
+                None
+            }
+        },
+        None => None,
+    };
+
+    errors.into_result()?;
+    // Every part has at least one sprite, and all of them loaded if nothing went wrong.
+    let size = size.unwrap();
+
+    let mut sprites = sprites.into_iter().map(|(handle, _)| handle);
+    let sprite = match &def.sprite {
+        DefSprite::Basic { .. } => PartSprite::Basic(sprites.next().unwrap()),
+        DefSprite::Animation { clips, .. } => PartSprite::Animation {
+            // `sprite_paths` lists the idle sprite first, then each clip's frames in order.
+            idle: sprites.next().unwrap(),
+            clips: clips
+                .iter()
+                .map(|clip| PartClip {
+                    name: clip.name.clone(),
+                    trigger: clip.trigger,
+                    frames: sprites.by_ref().take(clip.frames.len()).collect(),
+                    frame_time: clip.frame_time,
+                    mode: clip.mode(),
+                })
+                .collect(),
+            playing: None,
+            elapsed: 0.0,
+        },
+    };
+
+    let weapon = match (&def.weapon, weapon_sprite) {
+        (
+            Some(PartWeaponDef::Projectile {
+                projectile,
+                spread,
+                cooldown,
+            }),
+            Some((sprite, size)),
+        ) => Some(PartWeapon::Projectile {
+            spread: *spread,
+            cooldown: *cooldown,
+            last_shot: Instant::now(),
+            projectile: WeaponProjectile {
                 sprite,
                 size,
-                weapon,
-            });
-            for path in sprite_paths {
-                asset.add_dependency((&path).into());
+                damage: projectile.damage,
+                damage_type: projectile.damage_type,
+                velocity: projectile.velocity.unwrap_or_default(),
+                acceleration: projectile.acceleration.unwrap_or_default(),
+                lifetime: projectile.lifetime,
+                guidance: projectile.guidance,
+                explosion: projectile.explosion,
+            },
+        }),
+        (
+            Some(PartWeaponDef::Beam {
+                range,
+                dps,
+                width,
+                damage_type,
+                ..
+            }),
+            Some((sprite, _)),
+        ) => Some(PartWeapon::Beam {
+            range: *range,
+            dps: *dps,
+            width: *width,
+            sprite,
+            damage_type: damage_type.unwrap_or(DamageType::Energy),
+        }),
+        (
+            Some(PartWeaponDef::Melee {
+                damage,
+                knockback,
+                cooldown,
+                hitbox,
+                damage_type,
+            }),
+            _,
+        ) => Some(PartWeapon::Melee {
+            damage: *damage,
+            damage_type: damage_type.unwrap_or(DamageType::Kinetic),
+            knockback: *knockback,
+            cooldown: *cooldown,
+            last_hit: Instant::now(),
+            hitbox_size: hitbox
+                .map(|h| Vec2::from(h.size))
+                .unwrap_or(Vec2::new(size.0 as f32, size.1 as f32)),
+            hitbox_offset: hitbox
+                .and_then(|h| h.offset)
+                .map(Vec2::from)
+                .unwrap_or_default(),
+        }),
+        _ => None,
+    };
+
+    Ok(Part {
+        def,
+        sprite,
+        size,
+        weapon,
+        shape,
+    })
+}
+
+/// Resolves and builds every loaded part file, warning about the ones that fail. Each part comes
+/// with the handles of the files it was merged from, and the number of rejected files is
+/// returned alongside.
+fn build_parts(
+    handles: &[Handle<PartTemplate>],
+    templates: &Assets<PartTemplate>,
+    images: &Assets<Image>,
+) -> (Vec<(Part, Vec<Handle<PartTemplate>>)>, usize) {
+    let loaded = handles
+        .iter()
+        .filter_map(|handle| templates.get(handle).map(|template| (handle, template)))
+        .collect::<Vec<_>>();
+    let sources = loaded
+        .iter()
+        .map(|(_, template)| template.source.clone())
+        .collect::<Vec<_>>();
+
+    let mut parts = vec![];
+    let mut rejected = handles.len() - loaded.len();
+    for (i, resolved) in resolve_parts(&sources).into_iter().enumerate() {
+        let part = resolved.and_then(|resolved| {
+            let images = PartImages {
+                templates: resolved.chain.iter().map(|&j| loaded[j].1).collect(),
+                assets: images,
+            };
+            let chain = resolved
+                .chain
+                .iter()
+                .map(|&j| loaded[j].0.clone())
+                .collect();
+            build_part(resolved.def, &sources[i].file, &images).map(|part| (part, chain))
+        });
+
+        match part {
+            Ok(part) => parts.push(part),
+            Err(errors) => {
+                warn!("Failed to build part. Reason: {errors}");
+                rejected += 1;
             }
+        }
+    }
 
-            load_context.set_default_asset(asset);
+    (parts, rejected)
+}
 
-            Ok(())
+fn build_collider(def: &ColliderDef, sprites: &[&Image]) -> Result<Collider, String> {
+    let with_offset = |shape: Collider, offset: Option<(f32, f32)>| match offset {
+        Some(offset) => Collider::compound(vec![(
+            Vec2::from(offset).extend(0.0),
+            Quat::IDENTITY,
+            shape,
+        )]),
+        None => shape,
+    };
+
+    let points = match def {
+        ColliderDef::Box { size, offset } => {
+            let shape = Collider::cuboid(size.0 / 2.0, size.1 / 2.0, COLLIDER_HALF_DEPTH);
+            return Ok(with_offset(shape, *offset));
+        }
+        ColliderDef::Circle { radius, offset } => {
+            return Ok(with_offset(Collider::ball(*radius), *offset));
+        }
+        ColliderDef::Capsule { a, b, radius } => {
+            let (a, b) = (Vec2::from(*a).extend(0.0), Vec2::from(*b).extend(0.0));
+            return Ok(Collider::capsule(a, b, *radius));
+        }
+        ColliderDef::Convex { points } => points.iter().copied().map(Vec2::from).collect(),
+        ColliderDef::Auto { alpha_threshold } => {
+            let mut points = vec![];
+            for sprite in sprites {
+                points.extend(alpha_outline(sprite, alpha_threshold.unwrap_or(1))?);
+            }
+            if points.is_empty() {
+                return Err("sprite has no pixels above the alpha threshold".to_string());
+            }
+            points
+        }
+    };
+
+    let points = points
+        .into_iter()
+        .flat_map(|p| {
+            [
+                p.extend(-COLLIDER_HALF_DEPTH),
+                p.extend(COLLIDER_HALF_DEPTH),
+            ]
         })
+        .collect::<Vec<_>>();
+    Collider::convex_hull(&points).ok_or_else(|| "points don't form a convex shape".to_string())
+}
+
+/// The outer corners of the leftmost and rightmost opaque pixel in each row of `image`, relative
+/// to its middle. Enough to build the convex hull of the opaque pixels.
+fn alpha_outline(image: &Image, threshold: u8) -> Result<Vec<Vec2>, String> {
+    let descriptor = &image.texture_descriptor;
+    if !matches!(
+        descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    ) {
+        return Err(format!(
+            "auto colliders need an RGBA8 sprite (got {:?})",
+            descriptor.format
+        ));
     }
 
-    fn extensions(&self) -> &[&str] {
-        &["part.toml"]
+    let (w, h) = (
+        descriptor.size.width as usize,
+        descriptor.size.height as usize,
+    );
+    let half = Vec2::new(w as f32, h as f32) / 2.0;
+    let to_local = |x: usize, y: usize| Vec2::new(x as f32 - half.x, half.y - y as f32);
+
+    let mut points = vec![];
+    for y in 0..h {
+        let opaque = |x: &usize| image.data[(y * w + x) * 4 + 3] >= threshold;
+        let (left, right) = match ((0..w).find(opaque), (0..w).rev().find(opaque)) {
+            (Some(left), Some(right)) => (left, right),
+            _ => continue,
+        };
+
+        points.extend([
+            to_local(left, y),
+            to_local(left, y + 1),
+            to_local(right + 1, y),
+            to_local(right + 1, y + 1),
+        ]);
     }
+
+    Ok(points)
 }
 
 pub struct PartsLoadedEvent;
 
 pub fn track_parts_loaded(
-    mut c: Commands,
     assets: Res<AssetServer>,
-    parts: Res<Assets<Part>>,
+    templates: Res<Assets<PartTemplate>>,
+    images: Res<Assets<Image>>,
     mut table: ResMut<PartTable>,
-    handles: Option<Res<PartHandles>>,
+    handles: Res<PartHandles>,
     mut writer: EventWriter<PartsLoadedEvent>,
+    mut loaded: Local<bool>,
 ) {
-    let handles = match handles {
-        Some(handles) => handles,
-        None => return,
-    };
-    if let LoadState::Loaded = assets.get_group_load_state(handles.iter().map(|h| h.id)) {
-        table.0 = handles
-            .iter()
-            .map(|h| {
-                let part = parts.get(h).unwrap().clone();
-                (part.def.name.clone(), part)
-            })
-            .collect();
-        c.remove_resource::<PartHandles>();
-        writer.send(PartsLoadedEvent);
+    if *loaded {
+        return;
+    }
+
+    // A part that fails validation is rejected, but the rest of the parts are still usable.
+    let finished = handles.iter().all(|h| {
+        matches!(
+            assets.get_load_state(h),
+            LoadState::Loaded | LoadState::Failed
+        )
+    });
+    if !finished {
+        return;
+    }
+
+    // Parts can only be resolved once every file they might extend has loaded.
+    let (parts, rejected) = build_parts(&handles, &templates, &images);
+    table.0 = parts
+        .into_iter()
+        .map(|(part, _)| {
+            info!("Part {} loaded", &part.def.name);
+            (part.def.name.clone(), part)
+        })
+        .collect();
+
+    if rejected > 0 {
+        warn!("{rejected} part(s) failed to load and were skipped.");
+    }
+
+    *loaded = true;
+    writer.send(PartsLoadedEvent);
+}
+
+/// Rebuilds every part affected by a modified part file, including the parts that extend it,
+/// and updates every live entity spawned from them.
+pub fn reload_parts(
+    mut c: Commands,
+    mut events: EventReader<AssetEvent<PartTemplate>>,
+    handles: Res<PartHandles>,
+    templates: Res<Assets<PartTemplate>>,
+    images: Res<Assets<Image>>,
+    mut table: ResMut<PartTable>,
+    mut entities: Query<(
+        Entity,
+        &mut PartDef,
+        &mut PartStats,
+        &mut PartInfo,
+        &mut PartSprite,
+        &mut Handle<Image>,
+        &mut Collider,
+        &mut ColliderMassProperties,
+        &mut PartChildren,
+        Option<&PartParent>,
+    )>,
+) {
+    let modified = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Parts are only tracked once the initial load has finished.
+    if modified.is_empty() || table.is_empty() {
+        return;
+    }
+
+    let (parts, _) = build_parts(&handles, &templates, &images);
+    for (part, chain) in parts {
+        if !chain.iter().any(|handle| modified.contains(handle)) {
+            continue;
+        }
+
+        info!("Reloading part {}", &part.def.name);
+
+        let mut reseated = vec![];
+        for (
+            id,
+            mut def,
+            mut stats,
+            mut info,
+            mut sprite,
+            mut image,
+            mut collider,
+            mut mass_properties,
+            mut children,
+            parent,
+        ) in entities.iter_mut()
+        {
+            if def.name != part.def.name {
+                continue;
+            }
+
+            // Damage carries over, but a reload neither destroys a part nor revives a dead one.
+            let damage = def.stats.hp.saturating_sub(stats.hp);
+            let alive = stats.hp > 0;
+            *stats = part.def.stats;
+            stats.hp = match alive {
+                true => stats.hp.saturating_sub(damage).max(1),
+                false => 0,
+            };
+
+            *def = part.def.clone();
+            info.weapon = part.weapon.clone();
+            *sprite = part.sprite.clone();
+            *image = sprite.idle().clone();
+            *collider = part.collider();
+            *mass_properties = part.mass_properties();
+
+            for (i, child) in children.iter().enumerate() {
+                if let &Some(child) = child {
+                    if i < def.hardpoints.len() {
+                        c.attach_part(id, child, i);
+                    } else {
+                        c.detach_part(child);
+                    }
+                }
+            }
+            children.resize(def.hardpoints.len(), None);
+
+            if let Some(parent) = parent {
+                reseated.push((id, parent.0));
+            }
+        }
+
+        for (id, parent) in reseated {
+            let index = entities
+                .get(parent)
+                .ok()
+                .and_then(|(.., children, _)| children.iter().position(|&c| c == Some(id)));
+            if let Some(index) = index {
+                c.attach_part(parent, id, index);
+            }
+        }
+
+        table.insert(part.def.name.clone(), part);
     }
 }
 
 pub trait PartCommandsExt<'w, 's> {
     fn spawn_part<'a>(&'a mut self, part: &Part) -> EntityCommands<'w, 's, 'a>;
 
+    /// Spawns the robot a blueprint describes with its root at `transform`.
+    fn spawn_blueprint<'a>(
+        &'a mut self,
+        blueprint: &Blueprint,
+        parts: &PartTable,
+        transform: Transform,
+        additional_comp: Option<impl Component + Clone>,
+    ) -> Option<EntityCommands<'w, 's, 'a>>;
+
     fn attach_part(&mut self, parent: Entity, part: Entity, hardpoint: usize) -> &mut Self;
 
     fn detach_part(&mut self, part: Entity) -> &mut Self;
@@ -511,13 +1182,51 @@ impl<'w, 's> PartCommandsExt<'w, 's> for Commands<'w, 's> {
         let mut commands = self.spawn();
         let mut bundle = PartBundle::new(part);
         bundle.custom_data.part_tree_root = Some(commands.id());
+        let tree = PartTree::single(commands.id());
         commands.insert_bundle(bundle).insert_bundle((
             PartTreeRoot::default(),
+            tree,
             LockedAxes::TRANSLATION_LOCKED_Z | LockedAxes::ROTATION_LOCKED,
         ));
         commands
     }
 
+    fn spawn_blueprint<'a>(
+        &'a mut self,
+        blueprint: &Blueprint,
+        parts: &PartTable,
+        transform: Transform,
+        additional_comp: Option<impl Component + Clone>,
+    ) -> Option<EntityCommands<'w, 's, 'a>> {
+        let part = match parts.get(&blueprint.part) {
+            Some(part) => part,
+            None => {
+                warn!(
+                    "Failed to spawn blueprint {}. Reason: Unknown root part {}.",
+                    blueprint.name, blueprint.part
+                );
+                return None;
+            }
+        };
+
+        // The root has to be in place before its children are attached, since they're positioned
+        // relative to it.
+        let mut root = self.spawn_part(part);
+        root.insert(transform);
+        if let Some(comp) = additional_comp.clone() {
+            root.insert(comp);
+        }
+        spawn_slots(
+            &mut root,
+            &part.def,
+            &blueprint.children,
+            parts,
+            &additional_comp,
+        );
+
+        Some(root)
+    }
+
     fn attach_part(&mut self, parent: Entity, part: Entity, hardpoint: usize) -> &mut Self {
         self.add(move |world: &mut World| {
             let entity = match world.get_entity(parent) {
@@ -549,18 +1258,32 @@ impl<'w, 's> PartCommandsExt<'w, 's> for Commands<'w, 's> {
                     return;
                 },
             };
+
+            let fits = match (entity.get::<PartDef>(), world.get::<PartDef>(part)) {
+                (Some(parent_def), Some(def)) if !def.fits_on(&parent_def.hardpoints[hardpoint]) => {
+                    warn!("Failed to attach part to entity. Reason: Part {} does not fit hardpoint {} of part {}.", def.name, hardpoint, parent_def.name);
+                    false
+                },
+                _ => true,
+            };
+            if !fits {
+                // A part that stopped fitting where it sits, say after a reload, is knocked off
+                // rather than left on its old joint. One that was just spawned for the hardpoint
+                // isn't in any tree yet, so it's removed instead of left floating.
+                if world.get::<PartParent>(part).is_some() {
+                    detach_part(world, part);
+                } else if world.get::<PartTree>(part).is_none() {
+                    world.entity_mut(part).despawn_recursive();
+                }
+                return;
+            }
+
             let z = match order {
                 Order::Above => 0.1,
                 Order::Below => -0.1,
             };
 
-            let ownership = if entity.contains::<PlayerOwned>() {
-                1
-            } else if entity.contains::<EnemyOwned>() {
-                2
-            } else {
-                0
-            };
+            let faction = entity.get::<Faction>().copied();
 
             let entity_pos = entity.get::<Transform>().unwrap().translation;
 
@@ -569,13 +1292,16 @@ impl<'w, 's> PartCommandsExt<'w, 's> for Commands<'w, 's> {
                 let next = stack.pop().unwrap();
                 let mut next = world.entity_mut(next);
 
-                if ownership == 1 {
-                    next.insert(PlayerOwned);
-                } else if ownership == 2 {
-                    next.insert(EnemyOwned);
+                if let Some(faction) = faction {
+                    next.insert(faction);
+                } else {
+                    next.remove::<Faction>();
                 }
 
                 next.get_mut::<CustomPhysicsData>().unwrap().part_tree_root = part_tree_root;
+                if let Some(children) = next.get::<PartChildren>() {
+                    stack.extend(children.iter().filter_map(|&c| c));
+                }
             }
 
             let def = world.entity(part).get::<PartDef>().unwrap();
@@ -612,75 +1338,25 @@ impl<'w, 's> PartCommandsExt<'w, 's> for Commands<'w, 's> {
                     return;
                 },
             }
+
+            world.entity_mut(part).remove::<PartTree>();
+            rebuild_part_tree(world, parent);
+
+            if let Some(mut events) = world.get_resource_mut::<Events<PartAttached>>() {
+                events.send(PartAttached { part, parent, hardpoint });
+            }
         });
 
         self
     }
 
     fn detach_part(&mut self, part: Entity) -> &mut Self {
-        self.add(move |world: &mut World| {
-            let entity = match world.get_entity(part) {
-                Some(e) => e,
-                None => return,
-            };
-
-            if let Some(parent) = entity.get::<PartParent>() {
-                let parent_id = parent.0;
-                drop(parent);
-
-                if let Some(mut parent) = world.get_entity_mut(parent_id) {
-                    if let Some(mut children) = parent.get_mut::<PartChildren>() {
-                        _ = children
-                            .iter()
-                            .position(|e| e.is_some() && e.unwrap() == part)
-                            .map(|idx| children[idx] = None)
-                    }
-                }
-            }
-
-            if let Some(children) = world.entity(part).get::<PartChildren>() {
-                let children: Vec<Entity> = children.iter().filter_map(|&c| c).collect();
-                let mut stack = vec![];
-                for child in children {
-                    let mut child = world.entity_mut(child);
-                    child.remove::<PartParent>();
-                    child.remove::<ImpulseJoint>();
-                    child.insert(LockedAxes::TRANSLATION_LOCKED_Z | LockedAxes::ROTATION_LOCKED);
-                    let id = child.id();
-                    stack.clear();
-                    stack.push(id);
-                    while !stack.is_empty() {
-                        let next = stack.pop().unwrap();
-                        let mut next = world.entity_mut(next);
-                        next.remove::<PlayerOwned>();
-                        next.remove::<EnemyOwned>();
-                        next.get::<PartChildren>().map(|children| {
-                            children
-                                .iter()
-                                .filter_map(|&c| c)
-                                .for_each(|c| stack.push(c))
-                        });
-                    }
-                }
-
-                world
-                    .entity_mut(part)
-                    .get_mut::<PartChildren>()
-                    .unwrap()
-                    .iter_mut()
-                    .for_each(|c| *c = None);
-            }
-
-            let id = part;
-            let mut part = world.entity_mut(id);
-            part.remove::<ImpulseJoint>();
-            part.get_mut::<CustomPhysicsData>().unwrap().part_tree_root = None;
-        });
+        self.add(move |world: &mut World| detach_part(world, part));
         self
     }
 
     fn despawn_part(&mut self, part: Entity) -> &mut Self {
-        self.detach_part(part).entity(part).despawn();
+        self.detach_part(part).entity(part).despawn_recursive();
 
         self
     }
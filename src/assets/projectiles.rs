@@ -4,10 +4,12 @@ use bevy::prelude::*;
 use bevy::reflect::FromReflect;
 use bevy::utils::Instant;
 use bevy_rapier3d::prelude::*;
-use serde::{Deserialize, Serialize};
+use integra::defs::*;
 
-use crate::{CustomPhysicsData, Player};
+use crate::factions::Faction;
+use crate::{CustomPhysicsData, LastMousePosition, Player};
 
+use super::damage::*;
 use super::parts::*;
 
 pub struct ProjectilePlugin;
@@ -16,31 +18,66 @@ impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<WeaponProjectile>()
             .register_type::<Projectile>()
+            .add_event::<ExplosionEvent>()
             .add_system(apply_projectiles)
+            .add_system(apply_explosions.after(apply_projectiles))
+            .add_system(steer_projectiles)
             .add_system(despawn_old_projectiles);
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Reflect, FromReflect)]
-pub struct WeaponProjectileDef {
-    pub sprite_path: String,
-    pub damage: u32,
-    pub velocity: Option<f32>,
-    pub acceleration: Option<f32>,
-}
-
 #[derive(Clone, Debug, Reflect, FromReflect)]
 pub struct WeaponProjectile {
     pub sprite: Handle<Image>,
     pub size: (u32, u32),
     pub damage: u32,
+    pub damage_type: DamageType,
     pub velocity: f32,
     pub acceleration: f32,
+    pub lifetime: f32,
+    pub guidance: Option<GuidanceDef>,
+    pub explosion: Option<ExplosionDef>,
 }
 
 #[derive(Component, Reflect, FromReflect)]
 pub struct Projectile {
     pub damage: u32,
+    pub damage_type: DamageType,
+    pub explosion: Option<ExplosionDef>,
+}
+
+pub struct ExplosionEvent {
+    /// The part tree that fired the projectile, which is never hurt by its own explosions.
+    pub source: Option<Entity>,
+    /// The faction of the projectile, whose allies are never hurt by the explosion either.
+    pub faction: Option<Faction>,
+    /// The part the projectile struck, which always takes the full blast.
+    pub part: Option<Entity>,
+    pub position: Vec3,
+    pub damage: u32,
+    pub explosion: ExplosionDef,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ProjectileMotion {
+    pub acceleration: f32,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Guidance {
+    pub turn_rate: f32,
+    pub cone: f32,
+    pub remaining: f32,
+}
+
+impl From<GuidanceDef> for Guidance {
+    fn from(def: GuidanceDef) -> Self {
+        Self {
+            turn_rate: def.turn_rate,
+            cone: def.cone,
+            remaining: def.lifetime,
+        }
+    }
 }
 
 #[derive(Bundle)]
@@ -56,6 +93,7 @@ pub struct WeaponProjectileBundle {
     pub locked: LockedAxes,
     pub gravity: GravityScale,
     pub events: ActiveEvents,
+    pub motion: ProjectileMotion,
     lifetime: ProjectileLifetime,
 }
 
@@ -71,6 +109,8 @@ impl WeaponProjectileBundle {
             collider: Collider::cuboid(proj.size.0 as f32 / 2.0, proj.size.1 as f32 / 2.0, 50.0),
             projectile: Projectile {
                 damage: proj.damage,
+                damage_type: proj.damage_type,
+                explosion: proj.explosion,
             },
             custom_physics: CustomPhysicsData {
                 part_tree_root: Some(source),
@@ -86,57 +126,307 @@ impl WeaponProjectileBundle {
                 ..default()
             },
             events: ActiveEvents::COLLISION_EVENTS,
-            lifetime: ProjectileLifetime(Instant::now(), Duration::from_secs(30)),
+            motion: ProjectileMotion {
+                acceleration: proj.acceleration,
+            },
+            lifetime: ProjectileLifetime(Instant::now(), Duration::from_secs_f32(proj.lifetime)),
+        }
+    }
+}
+
+pub trait ProjectileCommandsExt {
+    fn spawn_projectile(
+        &mut self,
+        source: Entity,
+        proj: &WeaponProjectile,
+        pos: Vec3,
+        dir: Vec3,
+    ) -> &mut Self;
+}
+
+impl<'w, 's> ProjectileCommandsExt for Commands<'w, 's> {
+    fn spawn_projectile(
+        &mut self,
+        source: Entity,
+        proj: &WeaponProjectile,
+        pos: Vec3,
+        dir: Vec3,
+    ) -> &mut Self {
+        let mut projectile = self.spawn_bundle(WeaponProjectileBundle::new(source, proj, pos, dir));
+        if let Some(guidance) = proj.guidance {
+            projectile.insert(Guidance::from(guidance));
+        }
+
+        // Projectiles fight for whoever fired them.
+        let projectile = projectile.id();
+        self.add(move |world: &mut World| {
+            if let Some(&faction) = world.get::<Faction>(source) {
+                world.entity_mut(projectile).insert(faction);
+            }
+        });
+        self
+    }
+}
+
+/// Applies projectile acceleration and steers guided projectiles toward their target.
+///
+/// Player projectiles steer toward the cursor, everything else toward the nearest part tree hostile
+/// to its faction inside its acquisition cone.
+fn steer_projectiles(
+    time: Res<Time>,
+    mouse_pos: Res<LastMousePosition>,
+    mut projectiles: Query<
+        (
+            &mut Velocity,
+            &mut Transform,
+            &ProjectileMotion,
+            Option<&mut Guidance>,
+            &CustomPhysicsData,
+            Option<&Faction>,
+        ),
+        With<Projectile>,
+    >,
+    roots: Query<(&GlobalTransform, &Faction), With<PartTreeRoot>>,
+    player: Query<(), With<Player>>,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut velocity, mut transform, motion, guidance, data, faction) in projectiles.iter_mut() {
+        if motion.acceleration == 0.0 && guidance.is_none() {
+            continue;
+        }
+
+        let pos = transform.translation.truncate();
+        let mut heading = velocity.linvel.truncate().normalize_or_zero();
+        if heading == Vec2::ZERO {
+            heading = (transform.rotation * Vec3::Y)
+                .truncate()
+                .normalize_or_zero();
+        }
+        let speed = (velocity.linvel.truncate().length() + motion.acceleration * dt).max(0.0);
+
+        if let Some(mut guidance) = guidance {
+            if guidance.remaining > 0.0 {
+                guidance.remaining -= dt;
+
+                let target = match (data.part_tree_root, faction) {
+                    (Some(source), _) if player.contains(source) => Some(mouse_pos.0),
+                    (_, Some(faction)) => roots
+                        .iter()
+                        .filter(|(_, other)| faction.is_hostile(**other))
+                        .map(|(tf, _)| tf.translation().truncate())
+                        .filter(|target| {
+                            heading.angle_between(*target - pos).abs()
+                                <= guidance.cone.to_radians() / 2.0
+                        })
+                        .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos))),
+                    _ => None,
+                };
+
+                if let Some(target) = target {
+                    let desired = (target - pos).normalize_or_zero();
+                    if desired != Vec2::ZERO {
+                        let max_turn = guidance.turn_rate.to_radians() * dt;
+                        let turn = heading.angle_between(desired).clamp(-max_turn, max_turn);
+                        heading = Vec2::from_angle(turn).rotate(heading);
+                    }
+                }
+            }
         }
+
+        if heading == Vec2::ZERO {
+            continue;
+        }
+
+        velocity.linvel = (heading * speed).extend(0.0);
+        transform.rotation = Quat::from_rotation_arc_2d(Vec2::Y, heading);
+    }
+}
+
+/// Orders the entities of a collision event so the one matching `first` comes first.
+///
+/// Also returns whether the collision started (`true`) or stopped (`false`).
+pub(super) fn collision_pair(
+    event: &CollisionEvent,
+    first: impl Fn(Entity) -> bool,
+) -> Option<(Entity, Entity, bool)> {
+    let (left, right, started) = match event {
+        &CollisionEvent::Started(left, right, _) => (left, right, true),
+        &CollisionEvent::Stopped(left, right, _) => (left, right, false),
+    };
+
+    if first(left) {
+        Some((left, right, started))
+    } else if first(right) {
+        Some((right, left, started))
+    } else {
+        None
     }
 }
 
 fn apply_projectiles(
     mut c: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    projectiles: Query<(Entity, &Projectile)>,
-    mut parts: Query<(Entity, &mut PartStats)>,
-    player: Query<(), With<Player>>,
+    projectiles: Query<(
+        Entity,
+        &Projectile,
+        &Transform,
+        &CustomPhysicsData,
+        Option<&Faction>,
+    )>,
+    parts: Query<(), With<PartStats>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut explosions: EventWriter<ExplosionEvent>,
 ) {
     for event in collision_events.iter() {
-        let (left, right) = match event {
-            &CollisionEvent::Started(left, right, _) => (left, right),
+        let (proj_id, part_id) = match collision_pair(event, |e| projectiles.contains(e)) {
+            Some((proj_id, part_id, true)) => (proj_id, part_id),
             _ => continue,
         };
 
-        let ((proj_id, projectile), (part_id, mut stats)) = if let Ok(p) = projectiles.get(left) {
-            if let Ok(stats) = parts.get_mut(right) {
-                (p, stats)
-            } else {
-                continue;
-            }
-        } else if let Ok(p) = projectiles.get(right) {
-            if let Ok(stats) = parts.get_mut(left) {
-                (p, stats)
-            } else {
-                continue;
-            }
-        } else {
+        let (_, projectile, tf, data, faction) = projectiles.get(proj_id).unwrap();
+        if !parts.contains(part_id) {
             continue;
-        };
+        }
 
         c.entity(proj_id).despawn_recursive();
 
-        stats.hp = stats.hp.saturating_sub(projectile.damage);
-        if stats.hp == 0 {
-            if !player.contains(part_id) {
-                c.despawn_part(part_id);
+        if let Some(explosion) = projectile.explosion {
+            explosions.send(ExplosionEvent {
+                source: data.part_tree_root,
+                faction: faction.copied(),
+                part: Some(part_id),
+                position: tf.translation,
+                damage: projectile.damage,
+                explosion,
+            });
+            continue;
+        }
+
+        damage.send(DamageEvent {
+            source: data.part_tree_root,
+            target: part_id,
+            amount: projectile.damage as f32,
+            kind: projectile.damage_type,
+            position: tf.translation,
+        });
+    }
+}
+
+fn apply_explosions(
+    mut events: EventReader<ExplosionEvent>,
+    ctx: Res<RapierContext>,
+    mut parts: Query<
+        (
+            &GlobalTransform,
+            &CustomPhysicsData,
+            Option<&Faction>,
+            &mut ExternalImpulse,
+        ),
+        With<PartStats>,
+    >,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for event in events.iter() {
+        let ExplosionDef {
+            radius,
+            falloff,
+            impulse,
+        } = event.explosion;
+
+        let mut hits = vec![];
+        ctx.intersections_with_shape(
+            event.position,
+            Quat::IDENTITY,
+            &Collider::ball(radius),
+            QueryFilter::new().exclude_sensors(),
+            |entity| {
+                hits.push(entity);
+                true
+            },
+        );
+        if let Some(part) = event.part {
+            if !hits.contains(&part) {
+                hits.push(part);
             }
         }
+
+        for part_id in hits {
+            let (tf, data, faction, mut part_impulse) = match parts.get_mut(part_id) {
+                Ok(p) => p,
+                _ => continue,
+            };
+            if event.source.is_some() && data.part_tree_root == event.source {
+                continue;
+            }
+            if let (Some(a), Some(b)) = (event.faction, faction) {
+                if a.is_allied(*b) {
+                    continue;
+                }
+            }
+
+            // Falloff is measured to the part's surface, so large parts aren't spared just
+            // because their center is far away.
+            let scale = if event.part == Some(part_id) {
+                1.0
+            } else {
+                let predicate = |entity: Entity| entity == part_id;
+                let distance = ctx
+                    .project_point(
+                        event.position,
+                        true,
+                        QueryFilter::new().exclude_sensors().predicate(&predicate),
+                    )
+                    .map_or(radius, |(_, projection)| {
+                        projection.point.distance(event.position)
+                    });
+                falloff.scale(distance / radius)
+            };
+
+            let dir = (tf.translation() - event.position)
+                .truncate()
+                .normalize_or_zero()
+                .extend(0.0);
+            part_impulse.impulse += dir * impulse * scale;
+
+            damage.send(DamageEvent {
+                source: event.source,
+                target: part_id,
+                amount: event.damage as f32 * scale,
+                kind: DamageType::Explosive,
+                position: event.position,
+            });
+        }
     }
 }
 
 #[derive(Component, Clone, Debug)]
 struct ProjectileLifetime(Instant, Duration);
 
-fn despawn_old_projectiles(mut c: Commands, projectiles: Query<(Entity, &ProjectileLifetime)>) {
-    for (id, projectile) in projectiles.iter() {
-        if projectile.0.elapsed() >= projectile.1 {
+fn despawn_old_projectiles(
+    mut c: Commands,
+    projectiles: Query<(
+        Entity,
+        &ProjectileLifetime,
+        &Projectile,
+        &Transform,
+        &CustomPhysicsData,
+        Option<&Faction>,
+    )>,
+    mut explosions: EventWriter<ExplosionEvent>,
+) {
+    for (id, lifetime, projectile, tf, data, faction) in projectiles.iter() {
+        if lifetime.0.elapsed() >= lifetime.1 {
+            if let Some(explosion) = projectile.explosion {
+                explosions.send(ExplosionEvent {
+                    source: data.part_tree_root,
+                    faction: faction.copied(),
+                    part: None,
+                    position: tf.translation,
+                    damage: projectile.damage,
+                    explosion,
+                });
+            }
             c.entity(id).despawn();
         }
     }
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use crate::factions::Faction;
+
+use super::damage::*;
+use super::parts::*;
+
+pub struct SalvagePlugin;
+
+impl Plugin for SalvagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, drop_salvage.after(apply_damage))
+            .add_system(age_salvage)
+            .add_system(clear_salvage_tint);
+    }
+}
+
+/// How long salvage can be grabbed after being dropped, in seconds.
+const SALVAGE_PICKUP_WINDOW: f32 = 20.0;
+/// How long salvage lies around before despawning, in seconds. Salvage fades out between the
+/// end of the pickup window and this.
+const SALVAGE_LIFETIME: f32 = 25.0;
+
+const SALVAGE_TINT: Color = Color::rgb(0.6, 1.0, 0.6);
+
+/// A loose part dropped by a destroyed enemy part, up for grabs by the player.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Salvage {
+    pub age: f32,
+}
+
+impl Salvage {
+    pub fn can_pick_up(&self) -> bool {
+        self.age < SALVAGE_PICKUP_WINDOW
+    }
+}
+
+/// Turns everything attached to a destroyed part hostile to the player into salvage.
+fn drop_salvage(
+    mut c: Commands,
+    mut events: EventReader<PartDestroyed>,
+    factions: Query<&Faction>,
+    parents: Query<&PartChildren>,
+    trees: PartTrees,
+) {
+    for event in events.iter() {
+        match factions.get(event.part) {
+            Ok(faction) if faction.is_hostile(Faction::PLAYER) => (),
+            _ => continue,
+        }
+
+        let children = match parents.get(event.part) {
+            Ok(children) => children.iter().filter_map(|&c| c),
+            _ => continue,
+        };
+        for part in children.flat_map(|child| trees.subtree(child)) {
+            c.entity(part).insert(Salvage::default());
+        }
+    }
+}
+
+fn age_salvage(
+    mut c: Commands,
+    time: Res<Time>,
+    mut salvage: Query<(Entity, &mut Salvage, &mut Sprite)>,
+) {
+    for (part, mut salvage, mut sprite) in salvage.iter_mut() {
+        salvage.age += time.delta_seconds();
+        if salvage.age >= SALVAGE_LIFETIME {
+            c.despawn_part(part);
+            continue;
+        }
+
+        let fade =
+            (salvage.age - SALVAGE_PICKUP_WINDOW) / (SALVAGE_LIFETIME - SALVAGE_PICKUP_WINDOW);
+        sprite.color = SALVAGE_TINT.with_a(1.0 - fade.clamp(0.0, 1.0));
+    }
+}
+
+fn clear_salvage_tint(removed: RemovedComponents<Salvage>, mut sprites: Query<&mut Sprite>) {
+    for part in removed.iter() {
+        if let Ok(mut sprite) = sprites.get_mut(part) {
+            sprite.color = Color::WHITE;
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use integra::defs::*;
+
+use crate::CustomPhysicsData;
+
+use super::parts::*;
+
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(init_shields).add_system(regenerate_shields);
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Shield {
+    pub capacity: f32,
+    pub current: f32,
+    pub regen: f32,
+    pub regen_delay: f32,
+    pub radius: f32,
+    pub since_hit: f32,
+}
+
+impl From<ShieldDef> for Shield {
+    fn from(def: ShieldDef) -> Self {
+        Self {
+            capacity: def.capacity,
+            current: def.capacity,
+            regen: def.regen,
+            regen_delay: def.regen_delay,
+            radius: def.radius,
+            since_hit: 0.0,
+        }
+    }
+}
+
+/// Keeps each part's `Shield` in sync with its definition, including after a hot reload.
+fn init_shields(mut c: Commands, parts: Query<(Entity, &PartDef), Changed<PartDef>>) {
+    for (part, def) in parts.iter() {
+        match def.shield {
+            Some(shield) => c.entity(part).insert(Shield::from(shield)),
+            None => c.entity(part).remove::<Shield>(),
+        };
+    }
+}
+
+fn regenerate_shields(
+    time: Res<Time>,
+    mut shields: Query<(&mut Shield, &CustomPhysicsData)>,
+    roots: Query<&PartTreeRoot>,
+) {
+    let dt = time.delta_seconds();
+    for (mut shield, data) in shields.iter_mut() {
+        shield.since_hit += dt;
+        if shield.since_hit >= shield.regen_delay {
+            let regen = shield.regen * tree_power(data.part_tree_root, &roots);
+            shield.current = (shield.current + regen * dt).min(shield.capacity);
+        }
+    }
+}
+
+/// Soaks up `damage` dealt to `part` at `position` with the shields covering it.
+///
+/// Walks from the part up through its `PartParent`s, draining the closest shields first. Returns
+/// the damage left over for the part itself.
+pub(super) fn absorb_damage(
+    part: Entity,
+    position: Vec3,
+    damage: f32,
+    parents: &Query<&PartParent>,
+    shields: &mut Query<(&mut Shield, &GlobalTransform)>,
+) -> f32 {
+    let mut remaining = damage;
+    let mut next = Some(part);
+
+    while let Some(current) = next {
+        if remaining <= 0.0 {
+            break;
+        }
+
+        if let Ok((mut shield, tf)) = shields.get_mut(current) {
+            if tf.translation().truncate().distance(position.truncate()) <= shield.radius {
+                let absorbed = remaining.min(shield.current);
+                shield.current -= absorbed;
+                shield.since_hit = 0.0;
+                remaining -= absorbed;
+            }
+        }
+
+        next = parents.get(current).ok().map(|parent| **parent);
+    }
+
+    remaining.max(0.0)
+}
@@ -0,0 +1,143 @@
+//! Lints every part file and prints a summary table, without opening a window.
+//!
+//! Usage: `integra-parts [ASSETS_DIR]` (defaults to `assets`). Build it with
+//! `--no-default-features` to leave out Bevy entirely.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use integra::defs::*;
+
+const PARTS_DIR: &str = "toml/parts";
+const PART_EXTENSION: &str = ".part.toml";
+
+fn main() -> ExitCode {
+    let assets = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("assets"));
+
+    let files = match part_files(&assets.join(PARTS_DIR)) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", assets.join(PARTS_DIR).display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut errors = PartErrors::default();
+    let mut sources = vec![];
+    for file in files.iter() {
+        match read_part(file) {
+            Ok(source) => sources.push(source),
+            Err(e) => errors.extend(e.0),
+        }
+    }
+
+    let mut defs = vec![];
+    for (source, resolved) in sources.iter().zip(resolve_parts(&sources)) {
+        match resolved.and_then(|resolved| check_part(&assets, &source.file, resolved.def)) {
+            Ok(def) => defs.push(def),
+            Err(e) => errors.extend(e.0),
+        }
+    }
+
+    print_table(&defs);
+
+    if errors.is_empty() {
+        println!("\n{} part(s) OK", defs.len());
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("\n{errors}");
+        ExitCode::FAILURE
+    }
+}
+
+fn part_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(PART_EXTENSION))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn read_part(file: &Path) -> Result<PartSource, PartErrors> {
+    match std::fs::read_to_string(file) {
+        Ok(text) => PartSource::parse(file, &text),
+        Err(e) => {
+            let mut errors = PartErrors::default();
+            errors.push(file, "", e.to_string());
+            Err(errors)
+        }
+    }
+}
+
+fn check_part(assets: &Path, file: &Path, def: PartDef) -> Result<PartDef, PartErrors> {
+    let mut errors = PartErrors::default();
+
+    if let Err(e) = def.validate(file) {
+        errors.extend(e.0);
+    }
+
+    let sprites = def.sprite_paths().into_iter().chain(
+        def.weapon
+            .as_ref()
+            .and_then(|weapon| weapon.sprite_path())
+            .map(|(field, path)| (field.to_string(), path)),
+    );
+    for (field, path) in sprites {
+        if !assets.join(path).is_file() {
+            errors.push(file, field, format!("sprite `{path}` does not exist"));
+        }
+    }
+
+    errors.into_result().map(|()| def)
+}
+
+fn print_table(defs: &[PartDef]) {
+    let name_width = defs
+        .iter()
+        .map(|def| def.name.len())
+        .max()
+        .unwrap_or_default()
+        .max("NAME".len());
+
+    println!(
+        "{:<name_width$}  {:>5}  {:>7}  {:>7}  {:>7}  {:>7}  {:>7}  {:>10}  {:>7}",
+        "NAME", "HP", "MASS", "SPEED", "ACCEL", "FORCE", "ENERGY", "HARDPOINTS", "DPS"
+    );
+    for def in defs {
+        println!(
+            "{:<name_width$}  {:>5}  {:>7}  {:>7}  {:>7}  {:>7}  {:>7}  {:>10}  {:>7}",
+            def.name,
+            def.stats.hp,
+            stat(Some(def.stats.mass)),
+            stat(def.stats.speed),
+            stat(def.stats.acceleration),
+            stat(def.stats.force),
+            stat(net_energy(&def.stats)),
+            def.hardpoints.len(),
+            stat(def.weapon.as_ref().map(|weapon| weapon.dps())),
+        );
+    }
+}
+
+/// Energy generated minus energy drawn, or `None` for parts that do neither.
+fn net_energy(stats: &PartStats) -> Option<f32> {
+    match (stats.energy, stats.energy_draw) {
+        (None, None) => None,
+        (energy, draw) => Some(energy.unwrap_or_default() - draw.unwrap_or_default()),
+    }
+}
+
+fn stat(value: Option<f32>) -> String {
+    match value {
+        Some(value) => format!("{value:.1}"),
+        None => "-".to_string(),
+    }
+}
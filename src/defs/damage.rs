@@ -0,0 +1,56 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+#[cfg(feature = "bevy")]
+use bevy::reflect::FromReflect;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum DamageType {
+    #[default]
+    #[serde(rename = "kinetic")]
+    Kinetic,
+    #[serde(rename = "energy")]
+    Energy,
+    #[serde(rename = "explosive")]
+    Explosive,
+}
+
+/// The fraction of each damage type a part shrugs off, from 0 (none) to 1 (all of it).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct Resistances {
+    #[serde(default)]
+    pub kinetic: f32,
+    #[serde(default)]
+    pub energy: f32,
+    #[serde(default)]
+    pub explosive: f32,
+}
+
+impl Resistances {
+    pub fn get(&self, kind: DamageType) -> f32 {
+        match kind {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Energy => self.energy,
+            DamageType::Explosive => self.explosive,
+        }
+    }
+
+    /// How much of `amount` gets through these resistances.
+    pub fn mitigate(&self, amount: f32, kind: DamageType) -> f32 {
+        amount * (1.0 - self.get(kind).clamp(0.0, 1.0))
+    }
+}
+
+impl std::ops::Add<Resistances> for Resistances {
+    type Output = Self;
+
+    fn add(self, rhs: Resistances) -> Self::Output {
+        Self {
+            kinetic: self.kinetic + rhs.kinetic,
+            energy: self.energy + rhs.energy,
+            explosive: self.explosive + rhs.explosive,
+        }
+    }
+}
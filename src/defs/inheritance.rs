@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::parts::PartDef;
+use super::validation::PartErrors;
+
+/// A part file as written, before the part it `extends` is merged in.
+#[derive(Clone, Debug)]
+pub struct PartSource {
+    pub file: PathBuf,
+    pub value: toml::Value,
+}
+
+impl PartSource {
+    pub fn parse(file: &Path, text: &str) -> Result<Self, PartErrors> {
+        let mut errors = PartErrors::default();
+        let value = match text.parse::<toml::Value>() {
+            Ok(value) => value,
+            Err(e) => {
+                errors.push(file, "", e.to_string());
+                return Err(errors);
+            }
+        };
+
+        // Every file needs its own name, or it would inherit its parent's and replace it.
+        match value.get("name") {
+            Some(toml::Value::String(_)) => (),
+            Some(_) => errors.push(file, "name", "must be a string"),
+            None => errors.push(file, "name", "must be set in every part file"),
+        }
+        match value.get("extends") {
+            Some(toml::Value::String(_)) | None => (),
+            Some(_) => errors.push(file, "extends", "must be the name of another part"),
+        }
+
+        errors.into_result().map(|()| Self {
+            file: file.to_path_buf(),
+            value,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        self.value
+            .get("name")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn extends(&self) -> Option<&str> {
+        self.value.get("extends").and_then(|name| name.as_str())
+    }
+
+    /// Every image path written in this file, whatever field it's in.
+    pub fn image_paths(&self) -> Vec<(String, String)> {
+        let mut paths = vec![];
+        collect_image_paths(&self.value, "", &mut paths);
+        paths
+    }
+}
+
+/// Fields that hold image paths, in sprites and weapons alike.
+const IMAGE_FIELDS: &[&str] = &["path", "idle", "frames", "sprite_path"];
+
+fn collect_image_paths(value: &toml::Value, field: &str, paths: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter() {
+                let field = match field {
+                    "" => key.clone(),
+                    _ => format!("{field}.{key}"),
+                };
+                match (IMAGE_FIELDS.contains(&key.as_str()), value) {
+                    (true, toml::Value::String(path)) => paths.push((field, path.clone())),
+                    (true, toml::Value::Array(array)) => {
+                        for (i, path) in array.iter().enumerate() {
+                            if let Some(path) = path.as_str() {
+                                paths.push((format!("{field}[{i}]"), path.to_string()));
+                            }
+                        }
+                    }
+                    _ => collect_image_paths(value, &field, paths),
+                }
+            }
+        }
+        toml::Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                collect_image_paths(value, &format!("{field}[{i}]"), paths);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// A part definition with everything it extends merged in.
+#[derive(Clone, Debug)]
+pub struct ResolvedPart {
+    pub def: PartDef,
+    /// Indices of the sources the definition was merged from, starting with its own file and
+    /// ending with the part at the top of the chain.
+    pub chain: Vec<usize>,
+}
+
+/// Resolves the `extends` chain of every source and deserializes the merged result.
+///
+/// Tables are merged key by key, with the extending part's values winning. Anything else,
+/// including arrays like `hardpoints`, is replaced outright.
+pub fn resolve_parts(sources: &[PartSource]) -> Vec<Result<ResolvedPart, PartErrors>> {
+    let mut by_name = HashMap::new();
+    for (i, source) in sources.iter().enumerate() {
+        by_name.entry(source.name()).or_insert(i);
+    }
+
+    (0..sources.len())
+        .map(|i| resolve_part(sources, &by_name, i))
+        .collect()
+}
+
+fn resolve_part(
+    sources: &[PartSource],
+    by_name: &HashMap<&str, usize>,
+    index: usize,
+) -> Result<ResolvedPart, PartErrors> {
+    let file = &sources[index].file;
+    let mut errors = PartErrors::default();
+
+    let mut chain = vec![index];
+    while let Some(parent) = sources[*chain.last().unwrap()].extends() {
+        let parent_index = match by_name.get(parent) {
+            Some(&i) => i,
+            None => {
+                errors.push(file, "extends", format!("unknown part `{parent}`"));
+                return Err(errors);
+            }
+        };
+        if chain.contains(&parent_index) {
+            errors.push(
+                file,
+                "extends",
+                format!("`{parent}` ends up extending itself"),
+            );
+            return Err(errors);
+        }
+        chain.push(parent_index);
+    }
+
+    let mut value = toml::Value::Table(toml::value::Table::new());
+    for &i in chain.iter().rev() {
+        merge_toml(&mut value, sources[i].value.clone());
+    }
+
+    match value.try_into::<PartDef>() {
+        Ok(def) => Ok(ResolvedPart { def, chain }),
+        Err(e) => {
+            errors.push(file, "", e.to_string());
+            Err(errors)
+        }
+    }
+}
+
+/// Merges `over` into `base`, recursing into tables and replacing everything else.
+pub fn merge_toml(base: &mut toml::Value, over: toml::Value) {
+    match (base, over) {
+        (toml::Value::Table(base), toml::Value::Table(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+        name = "Base"
+        origin = [0, 0]
+        direction = [0, 1]
+        hardpoints = [{ position = [0, 5], direction = [0, 1], order = "above" }]
+
+        [stats]
+        hp = 10
+        speed = 20
+
+        [sprite]
+        type = "basic"
+        path = "png/base.png"
+    "#;
+
+    fn source(name: &str, text: &str) -> PartSource {
+        PartSource::parse(Path::new(name), text).unwrap()
+    }
+
+    fn value(text: &str) -> toml::Value {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn merge_overrides_nested_tables_key_by_key() {
+        let mut base = value("[stats]\nhp = 10\nspeed = 20\n[sprite]\npath = \"a.png\"");
+        merge_toml(&mut base, value("[stats]\nhp = 30"));
+
+        assert_eq!(base["stats"]["hp"].as_integer(), Some(30));
+        assert_eq!(base["stats"]["speed"].as_integer(), Some(20));
+        assert_eq!(base["sprite"]["path"].as_str(), Some("a.png"));
+    }
+
+    #[test]
+    fn merge_replaces_arrays_outright() {
+        let mut base = value("fits = [1, 2, 3]");
+        merge_toml(&mut base, value("fits = [4]"));
+
+        assert_eq!(base, value("fits = [4]"));
+    }
+
+    #[test]
+    fn resolves_overrides_over_inherited_fields() {
+        let sources = [
+            source(
+                "child.part.toml",
+                "name = \"Child\"\nextends = \"Base\"\n[stats]\nhp = 30",
+            ),
+            source("base.part.toml", BASE),
+        ];
+
+        let resolved = resolve_parts(&sources).remove(0).unwrap();
+        assert_eq!(resolved.chain, vec![0, 1]);
+        assert_eq!(resolved.def.name, "Child");
+        assert_eq!(resolved.def.stats.hp, 30);
+        assert_eq!(resolved.def.stats.speed, Some(20.0));
+        assert_eq!(resolved.def.hardpoints.len(), 1);
+    }
+
+    #[test]
+    fn replaces_inherited_arrays() {
+        let sources = [
+            source(
+                "child.part.toml",
+                "name = \"Child\"\nextends = \"Base\"\nhardpoints = []",
+            ),
+            source("base.part.toml", BASE),
+        ];
+
+        let resolved = resolve_parts(&sources).remove(0).unwrap();
+        assert!(resolved.def.hardpoints.is_empty());
+    }
+
+    #[test]
+    fn reports_missing_parents() {
+        let sources = [source(
+            "child.part.toml",
+            "name = \"Child\"\nextends = \"Nothing\"",
+        )];
+
+        let errors = resolve_parts(&sources).remove(0).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "extends");
+        assert!(errors[0].reason.contains("unknown part `Nothing`"));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let sources = [
+            source("a.part.toml", "name = \"A\"\nextends = \"B\""),
+            source("b.part.toml", "name = \"B\"\nextends = \"A\""),
+        ];
+
+        for result in resolve_parts(&sources) {
+            let errors = result.unwrap_err();
+            assert_eq!(errors[0].field, "extends");
+            assert!(errors[0].reason.contains("extending itself"));
+        }
+    }
+
+    #[test]
+    fn rejects_files_without_a_name() {
+        let errors = PartSource::parse(Path::new("a.part.toml"), "extends = \"Base\"").unwrap_err();
+        assert_eq!(errors[0].field, "name");
+    }
+}
@@ -0,0 +1,13 @@
+mod damage;
+mod inheritance;
+mod modifiers;
+mod parts;
+mod projectiles;
+mod validation;
+
+pub use damage::*;
+pub use inheritance::*;
+pub use modifiers::*;
+pub use parts::*;
+pub use projectiles::*;
+pub use validation::*;
@@ -0,0 +1,99 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+#[cfg(feature = "bevy")]
+use bevy::reflect::FromReflect;
+use serde::{Deserialize, Serialize};
+
+use super::parts::PartStats;
+
+/// A stat that modifiers can scale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum ModifierStat {
+    #[serde(rename = "speed")]
+    Speed,
+    #[serde(rename = "acceleration")]
+    Acceleration,
+    #[serde(rename = "force")]
+    Force,
+    #[serde(rename = "fire_rate")]
+    FireRate,
+    #[serde(rename = "energy")]
+    Energy,
+    #[serde(rename = "energy_draw")]
+    EnergyDraw,
+}
+
+/// Which parts of a robot a modifier applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum ModifierScope {
+    /// Just the part declaring the modifier.
+    #[serde(rename = "self")]
+    Part,
+    /// The part declaring the modifier and everything attached below it.
+    #[serde(rename = "subtree")]
+    Subtree,
+    /// Every part of the robot.
+    #[serde(rename = "root")]
+    Root,
+}
+
+/// Scales a stat of the parts in `scope` by `percent`, so 20 is a 20% bonus and -10 a 10% penalty.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct ModifierDef {
+    pub stat: ModifierStat,
+    pub percent: f32,
+    pub scope: ModifierScope,
+}
+
+/// Every modifier that applies to a single part, as summed percentages per stat.
+///
+/// Percentages for the same stat are added together before being applied once, so two +10%
+/// bonuses make +20% and the order parts were attached in never changes the result.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StatModifiers {
+    pub speed: f32,
+    pub acceleration: f32,
+    pub force: f32,
+    pub fire_rate: f32,
+    pub energy: f32,
+    pub energy_draw: f32,
+}
+
+impl StatModifiers {
+    pub fn add(&mut self, modifier: &ModifierDef) {
+        let percent = match modifier.stat {
+            ModifierStat::Speed => &mut self.speed,
+            ModifierStat::Acceleration => &mut self.acceleration,
+            ModifierStat::Force => &mut self.force,
+            ModifierStat::FireRate => &mut self.fire_rate,
+            ModifierStat::Energy => &mut self.energy,
+            ModifierStat::EnergyDraw => &mut self.energy_draw,
+        };
+        *percent += modifier.percent;
+    }
+
+    /// What a stat is multiplied by, never less than zero.
+    pub fn multiplier(percent: f32) -> f32 {
+        (1.0 + percent / 100.0).max(0.0)
+    }
+
+    pub fn fire_rate(&self) -> f32 {
+        Self::multiplier(self.fire_rate)
+    }
+
+    /// `stats` with every modifier applied.
+    pub fn apply(&self, stats: PartStats) -> PartStats {
+        let scale = |value: Option<f32>, percent: f32| value.map(|v| v * Self::multiplier(percent));
+        PartStats {
+            speed: scale(stats.speed, self.speed),
+            acceleration: scale(stats.acceleration, self.acceleration),
+            force: scale(stats.force, self.force),
+            energy: scale(stats.energy, self.energy),
+            energy_draw: scale(stats.energy_draw, self.energy_draw),
+            ..stats
+        }
+    }
+}
@@ -0,0 +1,392 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+#[cfg(feature = "bevy")]
+use bevy::reflect::{FromReflect, TypeUuid};
+use serde::{Deserialize, Serialize};
+
+use super::damage::*;
+use super::modifiers::*;
+use super::projectiles::*;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum Order {
+    #[serde(rename = "above")]
+    Above,
+    #[serde(rename = "below")]
+    Below,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum SocketKind {
+    #[serde(rename = "limb")]
+    Limb,
+    #[serde(rename = "head")]
+    Head,
+    #[serde(rename = "weapon")]
+    Weapon,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct Socket {
+    pub kind: SocketKind,
+    /// Parts only fit sockets at least as large as they are. Defaults to 0, the smallest size.
+    #[serde(default)]
+    pub size: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct Hardpoint {
+    pub position: (f32, f32),
+    pub direction: (f32, f32),
+    pub order: Order,
+    /// Hardpoints without a socket accept any part.
+    pub socket: Option<Socket>,
+    /// The most force the joint on this hardpoint can take before the attached part tears off, in
+    /// mass times world units per second squared.
+    pub strength: Option<f32>,
+}
+
+/// What starts an animation clip.
+///
+/// `move` and `idle` follow what the part's robot is doing, while the rest are events that play
+/// their clip over whatever was showing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum AnimationTrigger {
+    #[serde(rename = "idle")]
+    Idle,
+    #[serde(rename = "move")]
+    Move,
+    #[serde(rename = "shoot")]
+    Shoot,
+    #[serde(rename = "damaged")]
+    Damaged,
+    #[serde(rename = "attached")]
+    Attached,
+}
+
+impl AnimationTrigger {
+    /// Whether the trigger is a state the part stays in rather than a one-off event.
+    pub fn is_state(&self) -> bool {
+        matches!(self, AnimationTrigger::Idle | AnimationTrigger::Move)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum PlayMode {
+    /// Starts over after the last frame. Event clips that loop keep playing until another event
+    /// interrupts them.
+    #[serde(rename = "loop")]
+    Loop,
+    /// Goes back to the idle sprite, or the clip for the part's current state, after the last
+    /// frame.
+    #[serde(rename = "once")]
+    Once,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct ClipDef {
+    pub name: String,
+    pub trigger: AnimationTrigger,
+    pub frames: Vec<String>,
+    /// How long each frame is shown, in seconds.
+    pub frame_time: f32,
+    /// Defaults to `loop` for `move` and `idle` clips, and `once` for the rest.
+    pub mode: Option<PlayMode>,
+}
+
+impl ClipDef {
+    pub fn mode(&self) -> PlayMode {
+        match self.mode {
+            Some(mode) => mode,
+            None if self.trigger.is_state() => PlayMode::Loop,
+            None => PlayMode::Once,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+#[serde(tag = "type")]
+pub enum DefSprite {
+    #[serde(rename = "basic")]
+    Basic { path: String },
+    /// Shows `idle` unless one of the clips is playing. Only the first clip for each trigger is
+    /// ever played.
+    #[serde(rename = "animation")]
+    Animation { idle: String, clips: Vec<ClipDef> },
+}
+
+impl DefSprite {
+    pub fn is_animation(&self) -> bool {
+        match self {
+            DefSprite::Basic { .. } => false,
+            DefSprite::Animation { .. } => true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Component, Reflect, FromReflect),
+    reflect(Component)
+)]
+pub struct PartStats {
+    pub hp: u32,
+    pub speed: Option<f32>,
+    pub acceleration: Option<f32>,
+    pub force: Option<f32>,
+    /// Defaults to 1.
+    #[serde(default = "default_mass")]
+    pub mass: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    /// Power the part generates.
+    pub energy: Option<f32>,
+    /// Power the part needs to run at full strength.
+    pub energy_draw: Option<f32>,
+}
+
+fn default_mass() -> f32 {
+    1.0
+}
+
+impl std::ops::Add<PartStats> for PartStats {
+    type Output = Self;
+
+    fn add(self, rhs: PartStats) -> Self::Output {
+        Self {
+            hp: self.hp + rhs.hp,
+            speed: Some(self.speed.unwrap_or_default() + rhs.speed.unwrap_or_default()),
+            acceleration: Some(
+                self.acceleration.unwrap_or_default() + rhs.acceleration.unwrap_or_default(),
+            ),
+            force: Some(self.force.unwrap_or_default() + rhs.force.unwrap_or_default()),
+            mass: self.mass + rhs.mass,
+            resistances: self.resistances + rhs.resistances,
+            energy: Some(self.energy.unwrap_or_default() + rhs.energy.unwrap_or_default()),
+            energy_draw: Some(
+                self.energy_draw.unwrap_or_default() + rhs.energy_draw.unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+impl PartStats {
+    /// The fraction of the energy draw that the generated energy covers, from 0 to 1.
+    pub fn power(&self) -> f32 {
+        let draw = self.energy_draw.unwrap_or_default();
+        if draw <= 0.0 {
+            return 1.0;
+        }
+        (self.energy.unwrap_or_default() / draw).clamp(0.0, 1.0)
+    }
+}
+
+impl std::ops::AddAssign<PartStats> for PartStats {
+    fn add_assign(&mut self, rhs: PartStats) {
+        *self = *self + rhs;
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+#[serde(tag = "type")]
+pub enum PartWeaponDef {
+    #[serde(rename = "projectile")]
+    Projectile {
+        spread: f32,
+        cooldown: f32,
+        projectile: WeaponProjectileDef,
+    },
+    /// A continuous hitscan weapon that damages the first part in its path every frame.
+    #[serde(rename = "beam")]
+    Beam {
+        range: f32,
+        dps: f32,
+        width: f32,
+        sprite_path: String,
+        /// Defaults to energy.
+        damage_type: Option<DamageType>,
+    },
+    /// Damages opposing parts that touch the part's hitbox.
+    #[serde(rename = "melee")]
+    Melee {
+        damage: u32,
+        knockback: f32,
+        cooldown: f32,
+        hitbox: Option<MeleeHitboxDef>,
+        /// Defaults to kinetic.
+        damage_type: Option<DamageType>,
+    },
+}
+
+/// The area a melee part deals damage in, relative to the part's center.
+///
+/// Defaults to the size of the part's sprite when omitted.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct MeleeHitboxDef {
+    pub size: (f32, f32),
+    pub offset: Option<(f32, f32)>,
+}
+
+impl PartWeaponDef {
+    pub fn sprite_path(&self) -> Option<(&'static str, &String)> {
+        match self {
+            PartWeaponDef::Projectile { projectile, .. } => {
+                Some(("weapon.projectile.sprite_path", &projectile.sprite_path))
+            }
+            PartWeaponDef::Beam { sprite_path, .. } => Some(("weapon.sprite_path", sprite_path)),
+            PartWeaponDef::Melee { .. } => None,
+        }
+    }
+
+    pub fn dps(&self) -> f32 {
+        match self {
+            PartWeaponDef::Projectile {
+                cooldown,
+                projectile,
+                ..
+            } => projectile.damage as f32 / cooldown.max(f32::EPSILON),
+            PartWeaponDef::Beam { dps, .. } => *dps,
+            PartWeaponDef::Melee {
+                damage, cooldown, ..
+            } => *damage as f32 / cooldown.max(f32::EPSILON),
+        }
+    }
+}
+
+/// The physical shape of a part. Positions are in pixels relative to the middle of the sprite.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+#[serde(tag = "type")]
+pub enum ColliderDef {
+    #[serde(rename = "box")]
+    Box {
+        size: (f32, f32),
+        offset: Option<(f32, f32)>,
+    },
+    #[serde(rename = "circle")]
+    Circle {
+        radius: f32,
+        offset: Option<(f32, f32)>,
+    },
+    #[serde(rename = "capsule")]
+    Capsule {
+        a: (f32, f32),
+        b: (f32, f32),
+        radius: f32,
+    },
+    #[serde(rename = "convex")]
+    Convex { points: Vec<(f32, f32)> },
+    /// The convex hull of every sprite pixel with at least `alpha_threshold` alpha (default 1).
+    #[serde(rename = "auto")]
+    Auto { alpha_threshold: Option<u8> },
+}
+
+/// Absorbs damage dealt to the part and every part below it in its tree.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct ShieldDef {
+    pub capacity: f32,
+    /// Capacity regained per second once the shield has recovered.
+    pub regen: f32,
+    /// Seconds after the last hit before the shield starts regenerating.
+    pub regen_delay: f32,
+    /// Hits further than this from the shield part aren't absorbed.
+    pub radius: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Component, TypeUuid, Reflect, FromReflect),
+    uuid = "c3eda9f1-b731-4156-ae80-173056a0f25b"
+)]
+pub struct PartDef {
+    pub name: String,
+    /// The name of a part to take every field this file doesn't set from.
+    pub extends: Option<String>,
+    pub origin: (f32, f32),
+    pub direction: (f32, f32),
+    pub stay_upright: Option<bool>,
+    pub chassis: Option<bool>,
+    pub sprite: DefSprite,
+    /// Defaults to a box the size of the sprite.
+    pub collider: Option<ColliderDef>,
+    pub stats: PartStats,
+    /// Offset of the center of mass from the middle of the sprite. Defaults to the middle.
+    pub center_of_mass: Option<(f32, f32)>,
+    pub hardpoints: Vec<Hardpoint>,
+    /// The sockets the part can be attached to. Parts that fit no sockets can only go on
+    /// hardpoints without one.
+    #[serde(default)]
+    pub fits: Vec<Socket>,
+    /// The most force the part's joint can take before it tears off, in mass times world units per
+    /// second squared. Unbreakable if neither this nor the hardpoint it sits on sets a strength.
+    pub joint_strength: Option<f32>,
+    pub weapon: Option<PartWeaponDef>,
+    pub shield: Option<ShieldDef>,
+    #[serde(default)]
+    pub modifiers: Vec<ModifierDef>,
+}
+
+impl PartDef {
+    /// Every sprite the part needs, paired with the field it was declared in.
+    pub fn sprite_paths(&self) -> Vec<(String, &String)> {
+        match &self.sprite {
+            DefSprite::Basic { path } => vec![("sprite.path".to_string(), path)],
+            DefSprite::Animation { idle, clips } => {
+                let mut paths = vec![("sprite.idle".to_string(), idle)];
+                for (i, clip) in clips.iter().enumerate() {
+                    paths.extend(
+                        clip.frames
+                            .iter()
+                            .enumerate()
+                            .map(|(j, path)| (format!("sprite.clips[{i}].frames[{j}]"), path)),
+                    );
+                }
+                paths
+            }
+        }
+    }
+
+    /// Whether the part can be attached to `hardpoint`.
+    pub fn fits_on(&self, hardpoint: &Hardpoint) -> bool {
+        match hardpoint.socket {
+            Some(socket) => self
+                .fits
+                .iter()
+                .any(|fit| fit.kind == socket.kind && fit.size <= socket.size),
+            None => true,
+        }
+    }
+
+    /// The strength of the joint holding this part onto `hardpoint`, the weaker of the two if both
+    /// set one.
+    pub fn joint_strength_on(&self, hardpoint: &Hardpoint) -> Option<f32> {
+        match (self.joint_strength, hardpoint.strength) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    #[cfg(feature = "bevy")]
+    pub fn hardpoints(&self) -> impl Iterator<Item = (Vec2, Vec2, Order)> + '_ {
+        self.hardpoints.iter().map(move |point| {
+            (
+                Vec2::from(point.position),
+                Vec2::from(point.direction).normalize(),
+                point.order,
+            )
+        })
+    }
+}
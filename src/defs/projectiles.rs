@@ -0,0 +1,76 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+#[cfg(feature = "bevy")]
+use bevy::reflect::FromReflect;
+use serde::{Deserialize, Serialize};
+
+use super::damage::*;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct WeaponProjectileDef {
+    pub sprite_path: String,
+    pub damage: u32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub velocity: Option<f32>,
+    pub acceleration: Option<f32>,
+    /// How long the projectile flies before expiring, in seconds. Defaults to 30.
+    #[serde(default = "default_lifetime")]
+    pub lifetime: f32,
+    pub guidance: Option<GuidanceDef>,
+    pub explosion: Option<ExplosionDef>,
+}
+
+fn default_lifetime() -> f32 {
+    30.0
+}
+
+/// Lets a projectile steer itself after being fired.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct GuidanceDef {
+    /// Maximum turn speed, in degrees per second.
+    pub turn_rate: f32,
+    /// Full width of the cone in front of the projectile that targets are acquired from, in degrees.
+    pub cone: f32,
+    /// How long the projectile steers for after being fired, in seconds.
+    pub lifetime: f32,
+}
+
+/// Makes a projectile damage everything around it when it hits something or expires.
+///
+/// Explosions always deal explosive damage, whatever the projectile's own damage type is.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub struct ExplosionDef {
+    pub radius: f32,
+    #[serde(default)]
+    pub falloff: Falloff,
+    pub impulse: f32,
+}
+
+/// How damage and impulse drop off between the center and the edge of an explosion.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect, FromReflect))]
+pub enum Falloff {
+    #[serde(rename = "none")]
+    None,
+    #[default]
+    #[serde(rename = "linear")]
+    Linear,
+    #[serde(rename = "quadratic")]
+    Quadratic,
+}
+
+impl Falloff {
+    /// The fraction of full strength at `t`, where 0 is the center and 1 is the edge.
+    pub fn scale(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Falloff::None => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+        }
+    }
+}
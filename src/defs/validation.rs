@@ -0,0 +1,446 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use super::parts::*;
+
+/// A single problem found in a part file.
+#[derive(Clone, Debug)]
+pub struct PartError {
+    pub file: PathBuf,
+    pub field: String,
+    pub reason: String,
+}
+
+impl PartError {
+    pub fn new(file: &Path, field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for PartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}: {}", self.file.display(), self.reason)
+        } else {
+            write!(
+                f,
+                "{}: `{}`: {}",
+                self.file.display(),
+                self.field,
+                self.reason
+            )
+        }
+    }
+}
+
+/// Every problem found in a part file, reported together.
+#[derive(Clone, Debug, Default)]
+pub struct PartErrors(pub Vec<PartError>);
+
+impl Deref for PartErrors {
+    type Target = Vec<PartError>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for PartErrors {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl PartErrors {
+    pub fn push(&mut self, file: &Path, field: impl Into<String>, reason: impl Into<String>) {
+        self.0.push(PartError::new(file, field, reason));
+    }
+
+    pub fn into_result(self) -> Result<(), PartErrors> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for PartErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} problem(s) found in part file", self.0.len())?;
+        for error in self.0.iter() {
+            write!(f, "\n    {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PartErrors {}
+
+fn check_direction(errors: &mut PartErrors, file: &Path, field: &str, direction: (f32, f32)) {
+    let (x, y) = direction;
+    if !x.is_finite() || !y.is_finite() {
+        errors.push(file, field, "must be finite");
+    } else if x == 0.0 && y == 0.0 {
+        errors.push(file, field, "must not be zero-length");
+    }
+}
+
+fn check_non_negative(errors: &mut PartErrors, file: &Path, field: &str, value: Option<f32>) {
+    if let Some(value) = value {
+        if !value.is_finite() || value < 0.0 {
+            errors.push(
+                file,
+                field,
+                format!("must be a finite, non-negative number (got {value})"),
+            );
+        }
+    }
+}
+
+fn check_path(errors: &mut PartErrors, file: &Path, field: &str, path: &str) {
+    if Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_none()
+    {
+        errors.push(
+            file,
+            field,
+            format!("sprite `{path}` has an invalid extension"),
+        );
+    }
+}
+
+impl PartDef {
+    /// Checks the definition for values that would break the part at runtime.
+    pub fn validate(&self, file: &Path) -> Result<(), PartErrors> {
+        let mut errors = PartErrors::default();
+
+        if self.name.trim().is_empty() {
+            errors.push(file, "name", "must not be empty");
+        }
+
+        check_direction(&mut errors, file, "direction", self.direction);
+
+        for (i, hardpoint) in self.hardpoints.iter().enumerate() {
+            check_direction(
+                &mut errors,
+                file,
+                &format!("hardpoints[{i}].direction"),
+                hardpoint.direction,
+            );
+            check_non_negative(
+                &mut errors,
+                file,
+                &format!("hardpoints[{i}].strength"),
+                hardpoint.strength,
+            );
+        }
+
+        check_non_negative(&mut errors, file, "joint_strength", self.joint_strength);
+
+        check_non_negative(&mut errors, file, "stats.speed", self.stats.speed);
+        check_non_negative(
+            &mut errors,
+            file,
+            "stats.acceleration",
+            self.stats.acceleration,
+        );
+        check_non_negative(&mut errors, file, "stats.force", self.stats.force);
+        check_non_negative(&mut errors, file, "stats.energy", self.stats.energy);
+        check_non_negative(
+            &mut errors,
+            file,
+            "stats.energy_draw",
+            self.stats.energy_draw,
+        );
+
+        if !self.stats.mass.is_finite() || self.stats.mass <= 0.0 {
+            errors.push(
+                file,
+                "stats.mass",
+                format!("must be greater than zero (got {})", self.stats.mass),
+            );
+        }
+
+        let resistances = self.stats.resistances;
+        for (field, value) in [
+            ("stats.resistances.kinetic", resistances.kinetic),
+            ("stats.resistances.energy", resistances.energy),
+            ("stats.resistances.explosive", resistances.explosive),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                errors.push(
+                    file,
+                    field,
+                    format!("must be between 0 and 1 (got {value})"),
+                );
+            }
+        }
+
+        match &self.sprite {
+            DefSprite::Basic { path } => check_path(&mut errors, file, "sprite.path", path),
+            DefSprite::Animation { idle, clips } => {
+                check_path(&mut errors, file, "sprite.idle", idle);
+                for (i, clip) in clips.iter().enumerate() {
+                    if clips[..i].iter().any(|other| other.trigger == clip.trigger) {
+                        errors.push(
+                            file,
+                            format!("sprite.clips[{i}].trigger"),
+                            "another clip already plays on this trigger",
+                        );
+                    }
+                    if !clip.frame_time.is_finite() || clip.frame_time <= 0.0 {
+                        errors.push(
+                            file,
+                            format!("sprite.clips[{i}].frame_time"),
+                            "must be greater than zero",
+                        );
+                    }
+                    if clip.frames.is_empty() {
+                        errors.push(
+                            file,
+                            format!("sprite.clips[{i}].frames"),
+                            "must contain at least one frame",
+                        );
+                    }
+                    for (j, path) in clip.frames.iter().enumerate() {
+                        check_path(
+                            &mut errors,
+                            file,
+                            &format!("sprite.clips[{i}].frames[{j}]"),
+                            path,
+                        );
+                    }
+                }
+            }
+        }
+
+        match &self.weapon {
+            Some(PartWeaponDef::Projectile {
+                spread,
+                cooldown,
+                projectile,
+            }) => {
+                if !spread.is_finite() || *spread <= 0.0 {
+                    errors.push(file, "weapon.spread", "must be greater than zero");
+                }
+                check_non_negative(&mut errors, file, "weapon.cooldown", Some(*cooldown));
+                check_non_negative(
+                    &mut errors,
+                    file,
+                    "weapon.projectile.velocity",
+                    projectile.velocity,
+                );
+                if !projectile.lifetime.is_finite() || projectile.lifetime <= 0.0 {
+                    errors.push(
+                        file,
+                        "weapon.projectile.lifetime",
+                        "must be greater than zero",
+                    );
+                }
+                check_path(
+                    &mut errors,
+                    file,
+                    "weapon.projectile.sprite_path",
+                    &projectile.sprite_path,
+                );
+                if let Some(explosion) = &projectile.explosion {
+                    if !explosion.radius.is_finite() || explosion.radius <= 0.0 {
+                        errors.push(
+                            file,
+                            "weapon.projectile.explosion.radius",
+                            "must be greater than zero",
+                        );
+                    }
+                    check_non_negative(
+                        &mut errors,
+                        file,
+                        "weapon.projectile.explosion.impulse",
+                        Some(explosion.impulse),
+                    );
+                }
+                if let Some(guidance) = &projectile.guidance {
+                    check_non_negative(
+                        &mut errors,
+                        file,
+                        "weapon.projectile.guidance.turn_rate",
+                        Some(guidance.turn_rate),
+                    );
+                    check_non_negative(
+                        &mut errors,
+                        file,
+                        "weapon.projectile.guidance.lifetime",
+                        Some(guidance.lifetime),
+                    );
+                    if !(guidance.cone > 0.0 && guidance.cone <= 360.0) {
+                        errors.push(
+                            file,
+                            "weapon.projectile.guidance.cone",
+                            "must be between 0 and 360 degrees",
+                        );
+                    }
+                }
+            }
+            Some(PartWeaponDef::Beam {
+                range,
+                dps,
+                width,
+                sprite_path,
+                ..
+            }) => {
+                if !range.is_finite() || *range <= 0.0 {
+                    errors.push(file, "weapon.range", "must be greater than zero");
+                }
+                if !width.is_finite() || *width <= 0.0 {
+                    errors.push(file, "weapon.width", "must be greater than zero");
+                }
+                check_non_negative(&mut errors, file, "weapon.dps", Some(*dps));
+                check_path(&mut errors, file, "weapon.sprite_path", sprite_path);
+            }
+            Some(PartWeaponDef::Melee {
+                knockback,
+                cooldown,
+                hitbox,
+                ..
+            }) => {
+                check_non_negative(&mut errors, file, "weapon.knockback", Some(*knockback));
+                check_non_negative(&mut errors, file, "weapon.cooldown", Some(*cooldown));
+                if let Some(hitbox) = hitbox {
+                    let (x, y) = hitbox.size;
+                    if !(x.is_finite() && y.is_finite() && x > 0.0 && y > 0.0) {
+                        errors.push(file, "weapon.hitbox.size", "must be greater than zero");
+                    }
+                }
+            }
+            None => (),
+        }
+
+        match &self.collider {
+            Some(ColliderDef::Box { size, .. }) => {
+                if !(size.0 > 0.0 && size.1 > 0.0) {
+                    errors.push(file, "collider.size", "must be greater than zero");
+                }
+            }
+            Some(ColliderDef::Circle { radius, .. }) => {
+                if !radius.is_finite() || *radius <= 0.0 {
+                    errors.push(file, "collider.radius", "must be greater than zero");
+                }
+            }
+            Some(ColliderDef::Capsule { radius, .. }) => {
+                if !radius.is_finite() || *radius <= 0.0 {
+                    errors.push(file, "collider.radius", "must be greater than zero");
+                }
+            }
+            Some(ColliderDef::Convex { points }) => {
+                if points.len() < 3 {
+                    errors.push(file, "collider.points", "must have at least 3 points");
+                }
+                for (i, (x, y)) in points.iter().enumerate() {
+                    if !x.is_finite() || !y.is_finite() {
+                        errors.push(file, format!("collider.points[{i}]"), "must be finite");
+                    }
+                }
+            }
+            Some(ColliderDef::Auto { .. }) | None => (),
+        }
+
+        for (i, modifier) in self.modifiers.iter().enumerate() {
+            if !modifier.percent.is_finite() || modifier.percent < -100.0 {
+                errors.push(
+                    file,
+                    format!("modifiers[{i}].percent"),
+                    format!("must be at least -100 (got {})", modifier.percent),
+                );
+            }
+        }
+
+        if let Some(shield) = &self.shield {
+            check_non_negative(&mut errors, file, "shield.capacity", Some(shield.capacity));
+            check_non_negative(&mut errors, file, "shield.regen", Some(shield.regen));
+            check_non_negative(
+                &mut errors,
+                file,
+                "shield.regen_delay",
+                Some(shield.regen_delay),
+            );
+            if !shield.radius.is_finite() || shield.radius <= 0.0 {
+                errors.push(file, "shield.radius", "must be greater than zero");
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(text: &str) -> PartDef {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_valid_part() {
+        let def = def(r#"
+            name = "Leg"
+            origin = [0, 0]
+            direction = [1, 0]
+            hardpoints = []
+
+            [stats]
+            hp = 10
+            speed = 30
+
+            [sprite]
+            type = "basic"
+            path = "png/leg.png"
+        "#);
+
+        assert!(def.validate(Path::new("leg.part.toml")).is_ok());
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let def = def(r#"
+            name = " "
+            origin = [0, 0]
+            direction = [0, 0]
+            hardpoints = []
+
+            [stats]
+            hp = 10
+            speed = -1
+            mass = 0
+
+            [stats.resistances]
+            kinetic = 2
+
+            [sprite]
+            type = "basic"
+            path = "png/leg"
+        "#);
+
+        let errors = def.validate(Path::new("leg.part.toml")).unwrap_err();
+        let fields = errors.iter().map(|e| e.field.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                "name",
+                "direction",
+                "stats.speed",
+                "stats.mass",
+                "stats.resistances.kinetic",
+                "sprite.path",
+            ]
+        );
+        assert!(errors.iter().all(|e| e.file == Path::new("leg.part.toml")));
+    }
+}
@@ -6,7 +6,8 @@ use rand::{thread_rng, Rng};
 
 use crate::ai::*;
 use crate::assets::*;
-use crate::{Enemy, EnemyOwned, Player, DAMPING_FACTOR};
+use crate::factions::Faction;
+use crate::{AppState, Enemy, Player, DAMPING_FACTOR};
 
 pub struct DirectorPlugin;
 
@@ -14,10 +15,16 @@ impl Plugin for DirectorPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SpawnTimer(0.0))
             .add_system(update_player_score)
-            .add_system(spawn);
+            .add_system(spawn)
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(reset_director));
     }
 }
 
+fn reset_director(mut c: Commands, mut timer: ResMut<SpawnTimer>) {
+    timer.0 = 0.0;
+    c.remove_resource::<PlayerScore>();
+}
+
 struct PlayerScore(f32);
 
 fn update_player_score(
@@ -53,6 +60,7 @@ fn spawn(
     mut timer: ResMut<SpawnTimer>,
     time: Res<Time>,
     score: Option<Res<PlayerScore>>,
+    behaviors: Res<BehaviorTable>,
     player: Query<&GlobalTransform, With<Player>>,
 ) {
     let player = player.get_single();
@@ -66,6 +74,9 @@ fn spawn(
     if parts.is_none() {
         return;
     }
+    if behaviors.is_empty() {
+        return;
+    }
 
     let player = player.unwrap();
     let score = score.unwrap().0;
@@ -83,17 +94,28 @@ fn spawn(
     let minimum = (score / 10.0) as usize;
     let range = (score / 20.0) as usize;
     info!("Part count stats: |Minimum: {minimum}| |Range: {range}|");
+
+    // Sorted so the same roll always picks the same archetype.
+    let mut archetypes = behaviors.keys().collect::<Vec<_>>();
+    archetypes.sort();
     for _ in 0..enemy_count {
         let part_count = thread_rng().gen_range(minimum..=minimum + range);
         let dir =
             Quat::from_axis_angle(Vec3::Z, thread_rng().gen_range(0.0..=std::f32::consts::TAU))
                 * Vec3::Y;
         let pos = player.translation() + dir * thread_rng().gen_range(1000.0..=1500.0);
-        generate_enemy(&mut c, pos, part_count, &parts);
+        let archetype = archetypes[thread_rng().gen_range(0..archetypes.len())];
+        generate_enemy(&mut c, pos, part_count, &parts, archetype);
     }
 }
 
-fn generate_enemy(c: &mut Commands, position: Vec3, part_count: usize, parts: &PartTable) {
+fn generate_enemy(
+    c: &mut Commands,
+    position: Vec3,
+    part_count: usize,
+    parts: &PartTable,
+    archetype: &str,
+) {
     let chassis_parts = parts
         .values()
         .filter(|p| p.def.chassis.unwrap_or_default())
@@ -104,7 +126,14 @@ fn generate_enemy(c: &mut Commands, position: Vec3, part_count: usize, parts: &P
     let remaining_parts = part_count;
     let enemy = c
         .spawn_part(chassis)
-        .insert_bundle((AggressiveAi, Enemy, EnemyOwned))
+        .insert_bundle((
+            Behavior {
+                archetype: archetype.to_string(),
+            },
+            Blackboard::default(),
+            Enemy,
+            Faction::ENEMY,
+        ))
         .insert_bundle(CharacterControllerBundle {
             transform: Transform::from_translation(position),
             settings: ControllerSettings {
@@ -129,28 +158,32 @@ fn generate_enemy(c: &mut Commands, position: Vec3, part_count: usize, parts: &P
         .hardpoints
         .iter()
         .enumerate()
-        .map(|(i, _)| (enemy, i))
+        .map(|(i, hardpoint)| (enemy, i, hardpoint))
         .collect();
 
     extend_part_tree(c, parts, remaining_parts, open_points);
 }
 
-fn extend_part_tree(
+fn extend_part_tree<'a>(
     c: &mut Commands,
-    parts: &PartTable,
+    parts: &'a PartTable,
     mut remaining_parts: usize,
-    mut open_points: Vec<(Entity, usize)>,
+    mut open_points: Vec<(Entity, usize, &'a Hardpoint)>,
 ) {
     while open_points.len() > 0 && remaining_parts > 0 {
-        let (entity, hardpoint) =
+        let (entity, index, hardpoint) =
             open_points.swap_remove(thread_rng().gen_range(0..open_points.len()));
-        let part = parts
+        let candidates = parts
             .values()
-            .nth(thread_rng().gen_range(0..parts.len()))
-            .unwrap();
+            .filter(|part| part.def.fits_on(hardpoint))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            continue;
+        }
+        let part = candidates[thread_rng().gen_range(0..candidates.len())];
 
         let mut child = c.entity(entity);
-        let child = child.spawn_part_on_hardpoint(part, hardpoint, Some(EnemyOwned));
+        let child = child.spawn_part_on_hardpoint(part, index, Some(Faction::ENEMY));
         let child = child.id();
 
         open_points.extend(
@@ -158,7 +191,7 @@ fn extend_part_tree(
                 .hardpoints
                 .iter()
                 .enumerate()
-                .map(|(i, _)| (child, i)),
+                .map(|(i, hardpoint)| (child, i, hardpoint)),
         );
 
         remaining_parts -= 1;
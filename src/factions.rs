@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+/// Which side a part tree fights for. Copied onto every part in the tree when it is attached.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub struct Faction(pub u8);
+
+impl Faction {
+    pub const PLAYER: Faction = Faction(0);
+    pub const ENEMY: Faction = Faction(1);
+
+    /// How this faction treats `other`. Factions are always allied with themselves, and neutral
+    /// toward anything missing from the relation table.
+    pub fn relation(self, other: Faction) -> Relation {
+        if self == other {
+            return Relation::Allied;
+        }
+
+        RELATIONS
+            .get(self.0 as usize)
+            .and_then(|row| row.get(other.0 as usize))
+            .copied()
+            .unwrap_or(Relation::Neutral)
+    }
+
+    pub fn is_hostile(self, other: Faction) -> bool {
+        self.relation(other) == Relation::Hostile
+    }
+
+    pub fn is_allied(self, other: Faction) -> bool {
+        self.relation(other) == Relation::Allied
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+/// `RELATIONS[a][b]` is how faction `a` treats faction `b`.
+const RELATIONS: [[Relation; 2]; 2] = {
+    use Relation::*;
+    [
+        // Player
+        [Allied, Hostile],
+        // Enemy
+        [Hostile, Allied],
+    ]
+};
+
+/// Finds the closest part tree root hostile to `faction`.
+pub fn nearest_hostile<'a>(
+    faction: Faction,
+    position: Vec2,
+    roots: impl IntoIterator<Item = (Entity, &'a GlobalTransform, &'a Faction)>,
+) -> Option<(Entity, Vec2)> {
+    roots
+        .into_iter()
+        .filter(|(_, _, other)| faction.is_hostile(**other))
+        .map(|(root, tf, _)| (root, tf.translation().truncate()))
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        })
+}
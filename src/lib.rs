@@ -0,0 +1,7 @@
+//! Data definitions shared by the game and its content tooling.
+//!
+//! Nothing in here touches the renderer or the window, so tools like `integra-parts` can parse
+//! and check content on a headless machine. The Bevy derives the game relies on are only added
+//! with the `bevy` feature, which the default `game` feature turns on.
+
+pub mod defs;
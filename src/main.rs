@@ -1,5 +1,6 @@
 use ai::*;
 use assets::*;
+use bevy::asset::AssetServerSettings;
 use bevy::math::{vec2, vec3};
 use bevy::prelude::*;
 use bevy::render::texture::ImageSettings;
@@ -9,12 +10,14 @@ use bevy_mod_wanderlust::*;
 use bevy_rapier3d::prelude::*;
 use bevy_rapier3d::rapier::prelude::JointAxesMask;
 use director::*;
+use factions::*;
 use rand::prelude::*;
 use utils::*;
 
 mod ai;
 mod assets;
 mod director;
+mod factions;
 mod utils;
 
 #[derive(Component)]
@@ -24,6 +27,7 @@ struct MainCamera;
 enum AppState {
     Loading,
     Running,
+    GameOver,
 }
 
 #[derive(Component, Clone, Default, Reflect)]
@@ -36,28 +40,41 @@ struct CustomPhysicsData {
 
 struct CustomPhysicsHooks;
 
-impl PhysicsHooksWithQuery<&CustomPhysicsData> for CustomPhysicsHooks {
+type PhysicsHookData = (
+    &'static CustomPhysicsData,
+    Option<&'static Faction>,
+    Option<&'static Projectile>,
+);
+
+impl PhysicsHooksWithQuery<PhysicsHookData> for CustomPhysicsHooks {
     fn filter_contact_pair(
         &self,
         context: PairFilterContextView,
-        user_data: &Query<&CustomPhysicsData>,
+        user_data: &Query<PhysicsHookData>,
     ) -> Option<SolverFlags> {
-        let root1 = user_data
+        let default = CustomPhysicsData {
+            part_tree_root: None,
+            disable_collision: false,
+        };
+        let (root1, faction1, projectile1) = user_data
             .get(context.collider1())
-            .unwrap_or(&CustomPhysicsData {
-                part_tree_root: None,
-                disable_collision: false,
-            });
-        let root2 = user_data
+            .unwrap_or((&default, None, None));
+        let (root2, faction2, projectile2) = user_data
             .get(context.collider2())
-            .unwrap_or(&CustomPhysicsData {
-                part_tree_root: None,
-                disable_collision: false,
-            });
+            .unwrap_or((&default, None, None));
+
+        // Projectiles pass through anything allied with whoever fired them.
+        let allied = match (faction1, faction2) {
+            (Some(a), Some(b)) => a.is_allied(*b),
+            _ => false,
+        };
+
         if root1.part_tree_root == root2.part_tree_root {
             None
         } else if root1.disable_collision || root2.disable_collision {
             None
+        } else if allied && (projectile1.is_some() || projectile2.is_some()) {
+            None
         } else {
             Some(SolverFlags::all())
         }
@@ -68,9 +85,13 @@ fn main() {
     App::new()
         .add_state(AppState::Loading)
         .insert_resource(ImageSettings::default_nearest())
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: cfg!(not(target_arch = "wasm32")),
+            ..default()
+        })
         .add_plugins(DefaultPlugins)
         .add_plugin(EditorPlugin)
-        .add_plugin(RapierPhysicsPlugin::<&CustomPhysicsData>::default().with_physics_scale(32.0))
+        .add_plugin(RapierPhysicsPlugin::<PhysicsHookData>::default().with_physics_scale(32.0))
         .add_plugin(RapierDebugRenderPlugin::default())
         .insert_resource(PhysicsHooksWithQueryResource(Box::new(CustomPhysicsHooks)))
         .add_plugin(WanderlustPlugin)
@@ -80,6 +101,7 @@ fn main() {
         .add_plugin(assets::AssetPlugin)
         .insert_resource(LastMousePosition(Vec2::ZERO))
         .register_type::<CustomPhysicsData>()
+        .register_type::<Faction>()
         .add_event::<GrabModeEvent>()
         .add_startup_system(setup_marker_image)
         .add_system_set(SystemSet::on_update(AppState::Loading).with_system(start_game_when_ready))
@@ -91,22 +113,24 @@ fn main() {
         .add_system_set(
             SystemSet::on_update(AppState::Running)
                 .label("preupdate")
-                .with_system(track_mouse_position)
-                .with_system(detach_distant_parts),
+                .with_system(track_mouse_position),
         )
         .add_system_set(
             SystemSet::on_update(AppState::Running)
                 .after("preupdate")
                 .with_system(pass_inputs_to_controller)
-                .with_system(animate_moving_parts)
                 .with_system(apply_stats)
                 .with_system(fire_player_weapons)
                 .with_system(track_grabby_hand_to_mouse)
                 .with_system(grab_parts)
                 .with_system(show_markers)
                 .with_system(camera_track_player)
-                .with_system(fire_enemy_weapons),
+                .with_system(save_player_blueprint)
+                .with_system(fire_enemy_weapons)
+                .with_system(end_game_on_player_death),
         )
+        .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(clean_up_game))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(restart_game))
         .add_startup_system(setup.label("setup"))
         .run();
 }
@@ -124,26 +148,49 @@ fn setup(mut commands: Commands) {
 }
 
 fn start_game_when_ready(
-    mut reader: EventReader<PartsLoadedEvent>,
+    mut parts_loaded: EventReader<PartsLoadedEvent>,
+    mut blueprints_loaded: EventReader<BlueprintsLoadedEvent>,
+    mut behaviors_loaded: EventReader<BehaviorsLoadedEvent>,
+    mut loaded: Local<(bool, bool, bool)>,
     mut state: ResMut<State<AppState>>,
 ) {
-    if let Some(_) = reader.iter().next() {
+    loaded.0 |= parts_loaded.iter().next().is_some();
+    loaded.1 |= blueprints_loaded.iter().next().is_some();
+    loaded.2 |= behaviors_loaded.iter().next().is_some();
+
+    if loaded.0 && loaded.1 && loaded.2 {
         info!("All assets loaded. Starting game.");
 
         state.set(AppState::Running).unwrap();
     }
 }
 
-#[derive(Component)]
-struct PlayerOwned;
-
 const DAMPING_FACTOR: f32 = 4.0;
 
-fn start_game(mut c: Commands, parts: Res<PartTable>) {
-    let mut player = c.spawn_part(&parts["Box Chassis"]);
+const PLAYER_BLUEPRINT: &str = "Player";
+/// Kept out of the assets folder so saving doesn't trigger a reload. Copy the file into
+/// `assets/toml/blueprints` to use or share the build.
+const SAVED_BLUEPRINT_PATH: &str = "saves/saved.blueprint.toml";
+
+fn start_game(mut c: Commands, parts: Res<PartTable>, blueprints: Res<BlueprintTable>) {
+    let blueprint = match blueprints.get(PLAYER_BLUEPRINT) {
+        Some(blueprint) => blueprint,
+        None => {
+            error!("Failed to start game. Reason: Missing blueprint {PLAYER_BLUEPRINT}.");
+            return;
+        }
+    };
+    let mut player = match c.spawn_blueprint(
+        blueprint,
+        &parts,
+        Transform::from_xyz(0.0, 0.0, 0.0),
+        Some(Faction::PLAYER),
+    ) {
+        Some(player) => player,
+        None => return,
+    };
     player
         .insert_bundle((
-            Transform::from_xyz(0.0, 0.0, 0.0),
             ControllerSettings {
                 acceleration: 20.0,
                 max_speed: 100.0,
@@ -168,14 +215,61 @@ fn start_game(mut c: Commands, parts: Res<PartTable>) {
             Visibility::default(),
             ComputedVisibility::default(),
         ))
-        .insert_bundle((Player, PlayerOwned));
+        .insert(Player);
+}
 
-    for i in 0..4 {
-        player.spawn_part_on_hardpoint(&parts["Float Leg"], i, Some(PlayerOwned));
+fn end_game_on_player_death(
+    mut destroyed: EventReader<PartDestroyed>,
+    player: Query<(), With<Player>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if destroyed.iter().any(|event| player.contains(event.part)) {
+        info!("Player destroyed. Press R to restart.");
+        let _ = state.set(AppState::GameOver);
+    }
+}
+
+/// Despawns everything a run leaves behind, so the next one starts from a clean slate.
+fn clean_up_game(
+    mut c: Commands,
+    parts: Query<Entity, With<PartDef>>,
+    projectiles: Query<Entity, With<Projectile>>,
+    hand: Query<Entity, With<GrabbyHand>>,
+) {
+    for entity in parts.iter().chain(projectiles.iter()).chain(hand.iter()) {
+        c.entity(entity).despawn_recursive();
+    }
+}
+
+fn restart_game(input: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if input.just_pressed(KeyCode::R) {
+        state.set(AppState::Running).unwrap();
+    }
+}
+
+fn save_player_blueprint(
+    input: Res<Input<KeyCode>>,
+    player: Query<Entity, With<Player>>,
+    parts: Query<(&PartDef, &PartChildren)>,
+) {
+    if !input.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let player = match player.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
+
+    let blueprint = match Blueprint::capture("Saved", player, &parts) {
+        Some(blueprint) => blueprint,
+        None => return,
+    };
+
+    match blueprint.save(SAVED_BLUEPRINT_PATH) {
+        Ok(()) => info!("Saved player blueprint to {SAVED_BLUEPRINT_PATH}"),
+        Err(e) => warn!("Failed to save player blueprint. Reason: {e}"),
     }
-    player
-        .spawn_part_on_hardpoint(&parts["Box Head"], 4, Some(PlayerOwned))
-        .spawn_part_on_hardpoint(&parts["Blaster"], 4, Some(PlayerOwned));
 }
 
 fn pass_inputs_to_controller(
@@ -200,70 +294,18 @@ fn pass_inputs_to_controller(
     player.single_mut().movement = vector;
 }
 
-fn animate_moving_parts(
-    roots: Query<(Entity, &ControllerInput), With<PartTreeRoot>>,
-    parents: Query<&PartChildren>,
-    mut parts: Query<(&mut PartSprite, &mut Handle<Image>)>,
-) {
-    for (root, input) in roots.iter() {
-        let mut stack = vec![root];
-        while stack.len() > 0 {
-            let next = stack.pop().unwrap();
-
-            match parents.get(next) {
-                Ok(children) => stack.extend(children.iter().filter_map(|&c| c)),
-                _ => (),
-            };
-
-            let (mut sprite, mut image) = match parts.get_mut(next) {
-                Ok(p) => p,
-                Err(_) => continue,
-            };
-
-            let next_sprite = match &mut *sprite {
-                PartSprite::Basic(_) => continue,
-                PartSprite::Animation {
-                    anim,
-                    current,
-                    rate,
-                    timer,
-                } => match anim {
-                    PartAnimation::OnMove { idle, sequence } => {
-                        if input.movement.length_squared() != 0.0 {
-                            *timer += 1;
-                            if timer == rate {
-                                *timer = 0;
-                                *current += 1;
-                            }
-
-                            sequence.wrapping_get(*current).unwrap().clone()
-                        } else {
-                            idle.clone()
-                        }
-                    }
-                    PartAnimation::OnShoot { idle, .. } => idle.clone(),
-                },
-            };
-
-            *image = next_sprite;
-        }
-    }
-}
-
 fn apply_stats(mut q: Query<(&PartTreeRoot, &mut ControllerSettings)>) {
     for (root, mut settings) in q.iter_mut() {
         settings.max_speed = root.cumulative_stats.speed.unwrap_or_default();
         settings.acceleration = root.cumulative_stats.acceleration.unwrap_or_default();
-        settings.max_acceleration_force = root.cumulative_stats.force.unwrap_or_default();
+        settings.max_acceleration_force =
+            root.cumulative_stats.force.unwrap_or_default() * root.power;
     }
 }
 
 #[derive(Component)]
 struct Enemy;
 
-#[derive(Component)]
-struct EnemyOwned;
-
 struct LastMousePosition(Vec2);
 
 fn track_mouse_position(
@@ -293,29 +335,24 @@ fn fire_player_weapons(
     mut c: Commands,
     mouse_button: Res<Input<MouseButton>>,
     mouse_pos: Res<LastMousePosition>,
-    player: Query<Entity, With<Player>>,
-    mut parts: Query<(&GlobalTransform, &mut PartInfo, Option<&PartChildren>)>,
+    player: Query<(Entity, &PartTree, &PartTreeRoot), With<Player>>,
+    mut parts: Query<(&GlobalTransform, &mut PartInfo)>,
+    mut beams: EventWriter<BeamFiredEvent>,
+    mut fired: EventWriter<WeaponFired>,
 ) {
     if !mouse_button.pressed(MouseButton::Left) {
         return;
     };
 
-    let player = player.single();
-
-    let mut stack = vec![player];
-    while !stack.is_empty() {
-        let next = stack.pop().unwrap();
+    let (player, tree, root) = player.single();
 
-        let (tf, mut info, children) = match parts.get_mut(next) {
+    for next in tree.parts() {
+        let (tf, mut info) = match parts.get_mut(next) {
             Ok(v) => v,
             _ => continue,
         };
 
-        match children {
-            Some(children) => stack.extend(children.iter().filter_map(|c| c.as_ref().cloned())),
-            _ => (),
-        }
-
+        let rate = root.power * info.fire_rate;
         if let Some(weapon) = &mut info.weapon {
             match weapon {
                 PartWeapon::Projectile {
@@ -324,22 +361,26 @@ fn fire_player_weapons(
                     cooldown,
                     last_shot,
                 } => {
-                    if last_shot.elapsed().as_secs_f32() >= *cooldown {
+                    if last_shot.elapsed().as_secs_f32() >= *cooldown / rate {
                         let dir = (mouse_pos.0 - tf.translation().truncate()).extend(0.0);
                         let spread = thread_rng()
                             .gen_range(-*spread / 2.0..*spread / 2.0)
                             .to_radians();
                         let dir = Quat::from_axis_angle(Vec3::Z, spread) * dir;
-                        let bundle = WeaponProjectileBundle::new(
-                            player,
-                            projectile,
-                            tf.translation() - Vec3::Z,
-                            dir,
-                        );
-                        c.spawn_bundle(bundle);
+                        c.spawn_projectile(player, projectile, tf.translation() - Vec3::Z, dir);
                         *last_shot = Instant::now();
+                        fired.send(WeaponFired { part: next });
                     }
                 }
+                PartWeapon::Beam { .. } => {
+                    beams.send(BeamFiredEvent {
+                        part: next,
+                        direction: (mouse_pos.0 - tf.translation().truncate()).extend(0.0),
+                    });
+                    fired.send(WeaponFired { part: next });
+                }
+                // Melee weapons hit whatever they touch, see `MeleePlugin`.
+                PartWeapon::Melee { .. } => (),
             }
         }
     }
@@ -347,40 +388,30 @@ fn fire_player_weapons(
 
 fn fire_enemy_weapons(
     mut c: Commands,
-    player: Query<&GlobalTransform, With<Player>>,
-    enemies: Query<(Entity, &GlobalTransform), With<AggressiveAi>>,
-    mut parts: Query<(&GlobalTransform, &mut PartInfo, Option<&PartChildren>)>,
+    enemies: Query<(Entity, &Blackboard, &PartTree, &PartTreeRoot)>,
+    mut parts: Query<(&GlobalTransform, &mut PartInfo)>,
+    mut beams: EventWriter<BeamFiredEvent>,
+    mut fired: EventWriter<WeaponFired>,
 ) {
-    let player = match player.get_single() {
-        Ok(player) => player,
-        _ => return,
-    };
-
-    for (enemy, tf) in enemies.iter() {
-        if tf
-            .translation()
-            .truncate()
-            .distance_squared(player.translation().truncate())
-            >= 300.0f32.powf(2.0)
-        {
+    for (enemy, blackboard, tree, root) in enemies.iter() {
+        if !blackboard.firing {
             continue;
         }
+        let target = blackboard.target_position;
 
-        let mut stack = vec![enemy];
-        while !stack.is_empty() {
-            let next = stack.pop().unwrap();
-
-            let (tf, mut info, children) = match parts.get_mut(next) {
+        for next in tree.parts() {
+            let (tf, mut info) = match parts.get_mut(next) {
                 Ok(v) => v,
                 _ => continue,
             };
 
-            match children {
-                Some(children) => stack.extend(children.iter().filter_map(|c| c.as_ref().cloned())),
-                _ => (),
-            }
-
+            let rate = root.power * info.fire_rate;
             if let Some(weapon) = &mut info.weapon {
+                // Each weapon holds fire until the target is within its own reach.
+                if tf.translation().truncate().distance(target) > weapon.range() {
+                    continue;
+                }
+
                 match weapon {
                     PartWeapon::Projectile {
                         spread,
@@ -388,24 +419,25 @@ fn fire_enemy_weapons(
                         cooldown,
                         last_shot,
                     } => {
-                        if last_shot.elapsed().as_secs_f32() >= *cooldown {
-                            let dir = (player.translation().truncate()
-                                - tf.translation().truncate())
-                            .extend(0.0);
+                        if last_shot.elapsed().as_secs_f32() >= *cooldown / rate {
+                            let dir = (target - tf.translation().truncate()).extend(0.0);
                             let spread = thread_rng()
                                 .gen_range(-*spread / 2.0..*spread / 2.0)
                                 .to_radians();
                             let dir = Quat::from_axis_angle(Vec3::Z, spread) * dir;
-                            let bundle = WeaponProjectileBundle::new(
-                                enemy,
-                                projectile,
-                                tf.translation() - Vec3::Z,
-                                dir,
-                            );
-                            c.spawn_bundle(bundle);
+                            c.spawn_projectile(enemy, projectile, tf.translation() - Vec3::Z, dir);
                             *last_shot = Instant::now();
+                            fired.send(WeaponFired { part: next });
                         }
                     }
+                    PartWeapon::Beam { .. } => {
+                        beams.send(BeamFiredEvent {
+                            part: next,
+                            direction: (target - tf.translation().truncate()).extend(0.0),
+                        });
+                        fired.send(WeaponFired { part: next });
+                    }
+                    PartWeapon::Melee { .. } => (),
                 }
             }
         }
@@ -448,11 +480,12 @@ fn grab_parts(
     ctx: Res<RapierContext>,
     mut parts: Query<&mut CustomPhysicsData, With<PartDef>>,
     roots: Query<&PartTreeRoot>,
-    parents: Query<&PartChildren>,
+    trees: PartTrees,
     hand: Query<(Entity, Option<&ImpulseJoint>), With<GrabbyHand>>,
     grabbed: Query<Entity, With<Grabbed>>,
-    player_owned: Query<(), With<PlayerOwned>>,
-    enemy_owned: Query<(), With<EnemyOwned>>,
+    salvage: Query<&Salvage>,
+    factions: Query<&Faction>,
+    player: Query<&Faction, With<Player>>,
     mut writer: EventWriter<GrabModeEvent>,
     markers: Query<(&GlobalTransform, &HardpointMarker)>,
 ) {
@@ -473,7 +506,7 @@ fn grab_parts(
             .unwrap()
             .part_tree_root
             .unwrap_or(grabbed);
-        set_collision(root, &parents, &mut parts, true);
+        set_collision(root, &trees, &mut parts, true);
         c.entity(grabbed).remove::<Grabbed>();
         writer.send(GrabModeEvent::Stopped);
 
@@ -512,7 +545,12 @@ fn grab_parts(
         _ => return,
     };
 
-    if enemy_owned.contains(part) || roots.contains(part) {
+    // Only loose parts and parts from the player's own faction can be grabbed.
+    let faction = player.get_single().copied().unwrap_or(Faction::PLAYER);
+    if matches!(factions.get(part), Ok(other) if *other != faction) || roots.contains(part) {
+        return;
+    }
+    if matches!(salvage.get(part), Ok(salvage) if !salvage.can_pick_up()) {
         return;
     }
 
@@ -523,29 +561,25 @@ fn grab_parts(
     c.detach_part(part);
     c.entity(part).insert(Grabbed);
 
-    set_collision(part, &parents, &mut parts, false);
+    set_collision(part, &trees, &mut parts, false);
     writer.send(GrabModeEvent::Started(part));
+
+    // Picking salvage up claims it, along with everything still attached to it.
+    for next in trees.subtree(part) {
+        c.entity(next).remove::<Salvage>();
+    }
 }
 
 fn set_collision(
     root: Entity,
-    parents: &Query<&PartChildren>,
+    trees: &PartTrees,
     parts: &mut Query<&mut CustomPhysicsData, With<PartDef>>,
     collision: bool,
 ) {
-    let mut stack = vec![root];
-    while !stack.is_empty() {
-        let next = stack.pop().unwrap();
-
-        match parents.get(next) {
-            Ok(children) => children
-                .iter()
-                .filter_map(|&c| c)
-                .for_each(|c| stack.push(c)),
-            _ => (),
+    for next in trees.subtree(root) {
+        if let Ok(mut data) = parts.get_mut(next) {
+            data.disable_collision = !collision;
         }
-
-        parts.get_mut(next).unwrap().disable_collision = !collision;
     }
 }
 
@@ -606,14 +640,17 @@ fn setup_marker_image(mut c: Commands, ass: Res<AssetServer>) {
 fn show_markers(
     mut c: Commands,
     mut reader: EventReader<GrabModeEvent>,
-    parts: Query<(Entity, &PartDef, &PartChildren), With<PlayerOwned>>,
+    parts: Query<(Entity, &PartDef, &PartChildren, &Faction)>,
+    player: Query<&Faction, With<Player>>,
+    defs: Query<&PartDef>,
     marker_img: Res<MarkerImage>,
     markers: Query<Entity, With<HardpointMarker>>,
 ) {
     for event in reader.iter() {
         match event {
             GrabModeEvent::Started(grabbed) => {
-                for (part, def, children) in parts.iter() {
+                let faction = player.get_single().copied().unwrap_or(Faction::PLAYER);
+                for (part, def, children, _) in parts.iter().filter(|(.., f)| **f == faction) {
                     if part == *grabbed {
                         continue;
                     }
@@ -621,6 +658,10 @@ fn show_markers(
                         .iter()
                         .enumerate()
                         .filter_map(|(i, c)| c.is_none().then_some(i))
+                        .filter(|&i| match defs.get(*grabbed) {
+                            Ok(grabbed) => grabbed.fits_on(&def.hardpoints[i]),
+                            _ => true,
+                        })
                     {
                         let marker = HardpointBundle::new(def, part, i, marker_img.0.clone());
                         let marker = c.spawn_bundle(marker).id();
@@ -637,20 +678,6 @@ fn show_markers(
     }
 }
 
-fn detach_distant_parts(
-    mut c: Commands,
-    part: Query<&GlobalTransform, With<PartDef>>,
-    joints: Query<(Entity, &ImpulseJoint, &GlobalTransform), With<PartDef>>,
-) {
-    for (id, joint, tf) in joints.iter() {
-        let tf2 = part.get(joint.parent).unwrap();
-
-        if tf.translation().distance_squared(tf2.translation()) > 200.0f32.powf(2.0) {
-            c.detach_part(id);
-        }
-    }
-}
-
 fn camera_track_player(
     mut camera: Query<&mut Transform, With<MainCamera>>,
     player: Query<&GlobalTransform, With<Player>>,
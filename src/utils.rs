@@ -1,5 +1,7 @@
+use bevy::asset::{Asset, LoadState};
 use bevy::ecs::system::{CommandQueue, EntityCommands};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 
 pub struct UtilPlugin;
 
@@ -111,3 +113,40 @@ impl<T> VecExt<T> for Vec<T> {
         self.get_mut(index)
     }
 }
+
+/// The assets behind `handles` keyed by `name`, once every one of them has either loaded or
+/// failed. Files that failed to load are skipped with a warning, so one bad file doesn't hold up
+/// the rest.
+pub fn loaded_assets_by_name<T: Asset + Clone>(
+    server: &AssetServer,
+    assets: &Assets<T>,
+    handles: &[Handle<T>],
+    kind: &str,
+    name: impl Fn(&T) -> &str,
+) -> Option<HashMap<String, T>> {
+    let finished = handles.iter().all(|h| {
+        matches!(
+            server.get_load_state(h),
+            LoadState::Loaded | LoadState::Failed
+        )
+    });
+    if !finished {
+        return None;
+    }
+
+    let loaded = handles
+        .iter()
+        .filter_map(|h| match assets.get(h) {
+            Some(asset) => Some((name(asset).to_string(), asset.clone())),
+            None => {
+                let path = server
+                    .get_handle_path(h)
+                    .map(|path| path.path().display().to_string())
+                    .unwrap_or_default();
+                warn!("Failed to load {kind} {path}. Reason: Invalid {kind} file.");
+                None
+            }
+        })
+        .collect();
+    Some(loaded)
+}